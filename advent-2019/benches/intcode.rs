@@ -0,0 +1,70 @@
+//! Times the Intcode interpreter on day 2, with each word type and against the
+//! decoded-instruction cache, and times the two ways of solving part 2 against
+//! each other. Run with
+//! `cargo bench -p advent-2019`. Timings are only reported, since they vary too
+//! much from machine to machine to fail on.
+
+use std::{fmt::Debug, path::PathBuf, str::FromStr, time::{Duration, Instant}};
+
+use anyhow::*;
+
+use advent_2019::{intcode::{cached::CachedComputer, Computer, Word}, parsers, solutions::day2::part2};
+
+/// Every noun and verb, as in part 2.
+const RUNS: i64 = 10_000;
+
+/// The quickest of a few attempts, which is the least disturbed by noise.
+fn best<T>(mut f: impl FnMut() -> T) -> Duration {
+  (0..7).map(|_| {
+    let start = Instant::now();
+    std::hint::black_box(f());
+    start.elapsed()
+  }).min().unwrap()
+}
+
+fn day2() -> PathBuf {
+  [env!("CARGO_MANIFEST_DIR"), "input", "day2.txt"].iter().collect()
+}
+
+fn interpreter<W: Word>(name: &str) -> Result<()>
+where <W as FromStr>::Err: Debug {
+  let template: Computer<W> = parsers::intcode_as(day2())?;
+  let time = best(|| {
+    for run in 0..RUNS {
+      let mut computer = template.clone();
+      computer.memory[1] = W::from_i64(run / 100);
+      computer.memory[2] = W::from_i64(run % 100);
+      computer.run_until_blocked().unwrap();
+    }
+  });
+  println!("{:>6} words: {:>10.2?} for {} runs", name, time, RUNS);
+  Ok(())
+}
+
+fn cached() -> Result<()> {
+  let template = CachedComputer::new(parsers::intcode(day2())?);
+  let time = best(|| {
+    for run in 0..RUNS as isize {
+      let mut computer = template.clone();
+      computer.write(1, run / 100);
+      computer.write(2, run % 100);
+      computer.run_until_blocked().unwrap();
+    }
+  });
+  println!("{:>6} words: {:>10.2?} for {} runs, decoded once", "isize", time, RUNS);
+  Ok(())
+}
+
+fn main() -> Result<()> {
+  interpreter::<isize>("isize")?;
+  interpreter::<i64>("i64")?;
+  interpreter::<i128>("i128")?;
+  interpreter::<num::BigInt>("big")?;
+  cached()?;
+
+  let computer = parsers::intcode(day2())?;
+  let brute_force = best(|| part2::brute_force(&computer).unwrap());
+  let symbolic = best(|| part2::solve_symbolically(&computer).unwrap());
+  println!("part 2: {:.2?} by brute force, {:.2?} symbolically", brute_force, symbolic);
+  Ok(())
+}
@@ -0,0 +1,55 @@
+use std::{env, io::{self, BufRead, Write}};
+
+use anyhow::*;
+
+use advent_2019::{intcode::debugger::{Command, Debugger}, parsers};
+
+const HELP: &str = "\
+commands: step [n], continue, output, break <ip>, delete <ip>,
+          watch <addr> read|write|rw, unwatch <addr>, mem <addr> <len>,
+          input <value>, list, info, quit
+an empty line repeats the last command";
+
+fn main() -> Result<()> {
+  let arg = env::args().nth(1).ok_or_else(|| anyhow!("Usage: debug <program file | dayN>"))?;
+  let mut debugger = Debugger::new(parsers::intcode(parsers::locate_program(&arg))?);
+  println!("{}", HELP);
+  println!("{}", debugger.execute(Command::List)?);
+
+  let stdin = io::stdin();
+  let mut last = None;
+  loop {
+    print!("(intcode) ");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if stdin.lock().read_line(&mut line)? == 0 {
+      break;
+    }
+    let command = match line.trim() {
+      "" => match last {
+        Some(command) => command,
+        None => continue,
+      },
+      "help" => {
+        println!("{}", HELP);
+        continue;
+      },
+      text => match text.parse::<Command>() {
+        Ok(command) => command,
+        Err(e) => {
+          println!("{}", e);
+          continue;
+        },
+      },
+    };
+    if command == Command::Quit {
+      break;
+    }
+    match debugger.execute(command) {
+      Ok(message) => println!("{}", message),
+      Err(e) => println!("Error: {}", e),
+    }
+    last = Some(command);
+  }
+  Ok(())
+}
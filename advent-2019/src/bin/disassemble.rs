@@ -0,0 +1,14 @@
+use std::env;
+
+use anyhow::*;
+
+use advent_2019::{intcode::disassembler::disassemble, parsers};
+
+fn main() -> Result<()> {
+  let arg = env::args().nth(1).ok_or_else(|| anyhow!("Usage: disassemble <program file | dayN>"))?;
+  let computer = parsers::intcode(parsers::locate_program(&arg))?;
+  for line in disassemble(computer.memory.image()) {
+    println!("{}", line);
+  }
+  Ok(())
+}
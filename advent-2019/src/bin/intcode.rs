@@ -0,0 +1,121 @@
+use std::{collections::VecDeque, env, fmt::Debug, fs, io::{self, BufRead}, str::FromStr};
+
+use anyhow::*;
+
+use advent_2019::{
+  intcode::{cfg::Cfg, Computer, disassembler::disassemble, io::{FromFn, Sink}, trace::{Profiler, TraceWriter}, RunState, Word},
+  parsers,
+};
+
+const USAGE: &str = "Usage: intcode <program file | dayN> [input...] \
+  [--disassemble] [--cfg <dot file>] [--trace <file>] [--profile] [--step-limit <n>] [--strict] [--word isize|i64|i128|big]
+
+Inputs not given as arguments are read from stdin as they're needed.";
+
+/// How to run the program, once it's loaded.
+struct Options {
+  inputs: Vec<String>,
+  trace: Option<String>,
+  profile: bool,
+  step_limit: Option<usize>,
+  strict: bool,
+}
+
+fn main() -> Result<()> {
+  let mut program = None;
+  let mut inputs = Vec::new();
+  let mut cfg = None;
+  let mut word = "isize".to_string();
+  let (mut listing, mut trace, mut profile, mut step_limit, mut strict) = (false, None, false, None, false);
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--disassemble" => listing = true,
+      "--cfg" => cfg = Some(args.next().ok_or_else(|| anyhow!(USAGE))?),
+      "--trace" => trace = Some(args.next().ok_or_else(|| anyhow!(USAGE))?),
+      "--profile" => profile = true,
+      "--step-limit" => step_limit = Some(args.next().ok_or_else(|| anyhow!(USAGE))?.parse::<usize>()?),
+      "--strict" => strict = true,
+      "--word" => word = args.next().ok_or_else(|| anyhow!(USAGE))?,
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        return Ok(());
+      },
+      _ if program.is_none() => program = Some(arg),
+      _ => inputs.push(arg),
+    }
+  }
+  let program = program.ok_or_else(|| anyhow!(USAGE))?;
+  if trace.is_some() && profile {
+    bail!("--trace and --profile can't be used together");
+  }
+  let program = parsers::locate_program(&program);
+  if listing {
+    for line in disassemble(parsers::intcode(program)?.memory.image()) {
+      println!("{}", line);
+    }
+    return Ok(());
+  }
+  if let Some(path) = cfg {
+    return Ok(fs::write(path, Cfg::build(parsers::intcode(program)?.memory.image()).to_dot())?);
+  }
+
+  let options = Options { inputs, trace, profile, step_limit, strict };
+  match word.as_str() {
+    "isize" => run(parsers::intcode_as::<isize>(program)?, options),
+    "i64" => run(parsers::intcode_as::<i64>(program)?, options),
+    "i128" => run(parsers::intcode_as::<i128>(program)?, options),
+    "big" => run(parsers::intcode_as::<num::BigInt>(program)?, options),
+    _ => bail!("Unknown word type {}\n\n{}", word, USAGE),
+  }
+}
+
+fn run<W: Word>(mut computer: Computer<W>, options: Options) -> Result<()>
+where <W as FromStr>::Err: Debug {
+  let mut inputs = VecDeque::new();
+  for arg in options.inputs {
+    inputs.push_back(arg.parse::<W>().map_err(|_| anyhow!("Invalid input {}\n\n{}", arg, USAGE))?);
+  }
+  computer.strict = options.strict;
+  computer.step_limit = options.step_limit;
+
+  // Arguments first, then whatever numbers turn up on stdin, a line at a time
+  let stdin = io::stdin();
+  let next_input = || loop {
+    if let Some(value) = inputs.pop_front() {
+      return Some(value);
+    }
+    let mut line = String::new();
+    match stdin.lock().read_line(&mut line) {
+      Ok(0) | Err(_) => return None,
+      Ok(_) => for word in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|w| !w.is_empty()) {
+        match word.parse::<W>() {
+          Ok(value) => inputs.push_back(value),
+          Err(_) => eprintln!("Ignoring {}, which isn't a number", word),
+        }
+      },
+    }
+  };
+  let mut computer = computer.with_io(FromFn(next_input), Sink(|value| println!("{}", value)));
+
+  let state = match (options.trace, options.profile) {
+    (Some(path), false) => {
+      let mut tracer = TraceWriter::create(&path)?;
+      let state = computer.run_until_blocked_with(&mut tracer);
+      tracer.finish()?;
+      state?
+    },
+    (_, true) => {
+      let mut profiler = Profiler::default();
+      let state = computer.run_until_blocked_with(&mut profiler);
+      eprintln!("{}", profiler.summary());
+      state?
+    },
+    (None, false) => computer.run_until_blocked()?,
+  };
+  if state == RunState::NeedsInput {
+    bail!("Ran out of input at {} after {} steps", computer.instruction_pointer, computer.steps);
+  }
+  eprintln!("Halted after {} steps", computer.steps);
+  Ok(())
+}
@@ -0,0 +1,54 @@
+use std::{env, fs, io::{self, BufRead, Write}};
+
+use anyhow::*;
+
+use advent_2019::{intcode::ascii::{interact, Transcript}, parsers};
+
+const USAGE: &str = "Usage: terminal <program file | dayN> [--record <transcript>] [--replay <transcript>]";
+
+fn main() -> Result<()> {
+  let mut program = None;
+  let mut record = None;
+  let mut replay = vec![];
+  let mut args = env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--record" => record = Some(args.next().ok_or_else(|| anyhow!(USAGE))?),
+      "--replay" => {
+        let path = args.next().ok_or_else(|| anyhow!(USAGE))?;
+        replay = Transcript::inputs(&fs::read_to_string(path)?);
+      },
+      _ if program.is_none() => program = Some(arg),
+      _ => bail!(USAGE),
+    }
+  }
+  let program = program.ok_or_else(|| anyhow!(USAGE))?;
+  let mut computer = parsers::intcode(parsers::locate_program(&program))?;
+
+  // Replayed lines come first, echoed as if typed, then it's over to stdin
+  let stdin = io::stdin();
+  let mut replay = replay.into_iter();
+  let next_line = || {
+    if let Some(line) = replay.next() {
+      println!("{}", line);
+      return Some(line);
+    }
+    let mut line = String::new();
+    match stdin.lock().read_line(&mut line) {
+      Ok(0) | Err(_) => None,
+      Ok(_) => Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()),
+    }
+  };
+  let display = |text: &str| {
+    print!("{}", text);
+    io::stdout().flush().unwrap();
+  };
+
+  let mut transcript = Transcript::default();
+  let state = interact(&mut computer, display, next_line, &mut transcript)?;
+  println!("\n[{:?}]", state);
+  if let Some(path) = record {
+    fs::write(path, transcript.to_string())?;
+  }
+  Ok(())
+}
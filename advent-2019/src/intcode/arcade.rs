@@ -0,0 +1,229 @@
+use std::{collections::VecDeque, fmt};
+
+use anyhow::*;
+
+use super::{robot::{Grid, Point}, Computer, RunState, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+  Empty,
+  Wall,
+  Block,
+  Paddle,
+  Ball,
+}
+
+impl Tile {
+  pub fn from_value(value: Value) -> Result<Tile> {
+    Ok(match value {
+      0 => Tile::Empty,
+      1 => Tile::Wall,
+      2 => Tile::Block,
+      3 => Tile::Paddle,
+      4 => Tile::Ball,
+      _ => bail!("Unknown tile {}", value),
+    })
+  }
+
+  pub fn glyph(self) -> char {
+    match self {
+      Tile::Empty => ' ',
+      Tile::Wall => '#',
+      Tile::Block => '=',
+      Tile::Paddle => '_',
+      Tile::Ball => 'o',
+    }
+  }
+
+  pub fn colour(self) -> [u8; 3] {
+    match self {
+      Tile::Empty => [0, 0, 0],
+      Tile::Wall => [128, 128, 128],
+      Tile::Block => [200, 60, 40],
+      Tile::Paddle => [240, 240, 240],
+      Tile::Ball => [250, 210, 0],
+    }
+  }
+}
+
+/// An arcade cabinet's screen, drawn by `(x, y, tile)` triples of output. A
+/// triple at `score_at` sets the score instead of drawing a tile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+  pub frame: Grid<Tile>,
+  pub score: Value,
+  pub score_at: Point,
+}
+
+impl Default for Screen {
+  fn default() -> Self {
+    Screen { frame: Grid::default(), score: 0, score_at: (-1, 0) }
+  }
+}
+
+impl Screen {
+  pub fn with_score_at(mut self, point: Point) -> Self {
+    self.score_at = point;
+    self
+  }
+
+  /// Draws every complete triple in `output`, leaving any partial one there
+  /// for next time.
+  pub fn draw(&mut self, output: &mut VecDeque<Value>) -> Result<()> {
+    while output.len() >= 3 {
+      let point = (output[0] as i64, output[1] as i64);
+      let value = output[2];
+      output.drain(..3);
+      if point == self.score_at {
+        self.score = value;
+      } else {
+        self.frame.insert(point, Tile::from_value(value)?);
+      }
+    }
+    Ok(())
+  }
+
+  /// Where `tile` is, if it's on screen. If there are several, which one is
+  /// found is arbitrary.
+  pub fn find(&self, tile: Tile) -> Option<Point> {
+    self.frame.iter().find(|&(_, &t)| t == tile).map(|(point, _)| point)
+  }
+
+  pub fn count(&self, tile: Tile) -> usize {
+    self.frame.iter().filter(|&(_, &t)| t == tile).count()
+  }
+
+  /// The frame as a binary PPM image, with each tile `scale` pixels square.
+  /// Anything never drawn is black.
+  pub fn to_ppm(&self, scale: usize) -> Vec<u8> {
+    let ((left, top), (right, bottom)) = self.frame.bounds().unwrap_or(((0, 0), (-1, -1)));
+    let (width, height) = ((right - left + 1) as usize, (bottom - top + 1) as usize);
+    let mut image = format!("P6\n{} {}\n255\n", width * scale, height * scale).into_bytes();
+    for y in top..=bottom {
+      let row: Vec<u8> = (left..=right)
+        .flat_map(|x| {
+          let colour = self.frame.get((x, y)).map_or([0, 0, 0], |tile| tile.colour());
+          colour.repeat(scale)
+        })
+        .collect();
+      for _ in 0..scale {
+        image.extend(&row);
+      }
+    }
+    image
+  }
+}
+
+impl fmt::Display for Screen {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.frame.render(|_, tile| tile.map_or(' ', |tile| tile.glyph())))?;
+    writeln!(f, "Score: {}", self.score)
+  }
+}
+
+/// Plays a game until it halts, drawing to `screen` and asking `player` for
+/// the joystick position (-1 for left, 0 for neutral, 1 for right) whenever
+/// the game wants input. Returns the final score.
+pub fn play(computer: &mut Computer, screen: &mut Screen, mut player: impl FnMut(&Screen) -> Value) -> Result<Value> {
+  loop {
+    let state = computer.run_until_blocked()?;
+    screen.draw(&mut computer.output)?;
+    if state == RunState::Halted {
+      if !computer.output.is_empty() {
+        bail!("Game halted partway through drawing a tile: {:?}", computer.output);
+      }
+      return Ok(screen.score);
+    }
+    computer.input.push_back(player(screen));
+  }
+}
+
+/// A player that keeps the paddle under the ball.
+pub fn follow_ball(screen: &Screen) -> Value {
+  match (screen.find(Tile::Ball), screen.find(Tile::Paddle)) {
+    (Some((ball, _)), Some((paddle, _))) => (ball - paddle).signum() as Value,
+    _ => 0,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::assembler::assemble;
+
+  // The ball hangs at (3, 1) while the paddle moves along y = 2 as it's told,
+  // five times, then the score is set to 100
+  fn game() -> Computer {
+    assemble("
+      loop: OUTPUT [px]
+            OUTPUT #2
+            OUTPUT #3
+            OUTPUT #3
+            OUTPUT #1
+            OUTPUT #4
+            INPUT -> [j]
+            OUTPUT [px]
+            OUTPUT #2
+            OUTPUT #0
+            ADD [px], [j] -> [px]
+            ADD [n], #-1 -> [n]
+            JUMPIFTRUE [n], #loop
+            OUTPUT [px]
+            OUTPUT #2
+            OUTPUT #3
+            OUTPUT #-1
+            OUTPUT #0
+            OUTPUT #100
+            HALT
+      px:   DATA 0
+      j:    DATA 0
+      n:    DATA 5
+    ").unwrap().into()
+  }
+
+  #[test]
+  fn draws_triples() {
+    let mut screen = Screen::default();
+    let mut output = vec![0, 0, 1, 1, 0, 2, -1, 0, 12, 2, 0].into_iter().collect();
+    screen.draw(&mut output).unwrap();
+    assert_eq!(Some(&Tile::Block), screen.frame.get((1, 0)));
+    assert_eq!(12, screen.score);
+    assert_eq!(vec![2, 0], output.into_iter().collect::<Vec<_>>());
+    assert_eq!("#=\nScore: 12\n", screen.to_string());
+
+    let mut screen = Screen::default().with_score_at((9, 9));
+    screen.draw(&mut vec![-1, 0, 2, 9, 9, 7].into_iter().collect()).unwrap();
+    assert_eq!(Some(&Tile::Block), screen.frame.get((-1, 0)));
+    assert_eq!(7, screen.score);
+    assert!(screen.draw(&mut vec![0, 0, 5].into_iter().collect()).is_err());
+  }
+
+  #[test]
+  fn renders_ppm() {
+    let mut screen = Screen::default();
+    screen.draw(&mut vec![0, 0, 1, 1, 0, 4].into_iter().collect()).unwrap();
+    let image = screen.to_ppm(2);
+    let header = b"P6\n4 2\n255\n";
+    assert_eq!(header, &image[..header.len()]);
+    let pixels = &image[header.len()..];
+    assert_eq!(4 * 2 * 3, pixels.len());
+    assert_eq!([128, 128, 128, 128, 128, 128, 250, 210, 0, 250, 210, 0], pixels[..12]);
+    assert_eq!(pixels[..12], pixels[12..]);
+  }
+
+  #[test]
+  fn plays_itself() {
+    let mut screen = Screen::default();
+    let mut paddles = vec![];
+    let score = play(&mut game(), &mut screen, |screen| {
+      paddles.push(screen.find(Tile::Paddle).unwrap().0);
+      follow_ball(screen)
+    }).unwrap();
+    assert_eq!(100, score);
+    assert_eq!(vec![0, 1, 2, 3, 3], paddles);
+    assert_eq!("   o\n   _\nScore: 100\n", screen.to_string());
+
+    let mut screen = Screen::default();
+    assert!(play(&mut vec![104, 1, 104, 2, 99].into(), &mut screen, follow_ball).is_err());
+  }
+}
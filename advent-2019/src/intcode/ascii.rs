@@ -0,0 +1,185 @@
+use std::fmt;
+
+use anyhow::Result;
+
+use super::{Computer, RunState, Value};
+
+/// A run of output, split into what's printable and what isn't.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ascii {
+  Text(String),
+  /// Anything outside of ASCII, which is usually the puzzle answer.
+  Value(Value),
+}
+
+impl Computer {
+  pub fn push_str(&mut self, text: &str) {
+    self.input.extend(text.chars().map(|c| c as Value));
+  }
+
+  pub fn push_line(&mut self, line: &str) {
+    self.push_str(line);
+    self.input.push_back('\n' as Value);
+  }
+
+  pub fn drain_ascii(&mut self) -> Vec<Ascii> {
+    let mut chunks = vec![];
+    for value in self.output.drain(..) {
+      match (value, chunks.last_mut()) {
+        (0..=127, Some(Ascii::Text(text))) => text.push(value as u8 as char),
+        (0..=127, _) => chunks.push(Ascii::Text((value as u8 as char).to_string())),
+        _ => chunks.push(Ascii::Value(value)),
+      }
+    }
+    chunks
+  }
+
+  /// Drains all output as text, writing anything that isn't ASCII as a number.
+  pub fn drain_text(&mut self) -> String {
+    self.drain_ascii().into_iter().map(|chunk| match chunk {
+      Ascii::Text(text) => text,
+      Ascii::Value(value) => value.to_string(),
+    }).collect()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entry {
+  Output(String),
+  Input(String),
+}
+
+/// A record of an interactive session. It prints as the program's output with
+/// each line typed in shown as `> line`, and those lines can be read back out
+/// to replay the session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+  entries: Vec<Entry>,
+}
+
+impl Transcript {
+  /// The lines typed in during a printed transcript. Any output line that
+  /// happens to start with `> ` will be read as input too.
+  pub fn inputs(transcript: &str) -> Vec<String> {
+    transcript.lines().filter_map(|line| line.strip_prefix("> ")).map(str::to_string).collect()
+  }
+}
+
+impl fmt::Display for Transcript {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut at_line_start = true;
+    for entry in &self.entries {
+      match entry {
+        Entry::Output(text) => {
+          write!(f, "{}", text)?;
+          at_line_start = text.ends_with('\n');
+        },
+        Entry::Input(line) => {
+          if !at_line_start {
+            writeln!(f)?;
+          }
+          writeln!(f, "> {}", line)?;
+          at_line_start = true;
+        },
+      }
+    }
+    if !at_line_start {
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+}
+
+/// Runs an ASCII program interactively: each time it blocks, whatever it
+/// printed goes to `display` and `next_line` supplies the next line of input.
+/// Stops when the program halts, or when `next_line` runs out.
+pub fn interact(
+  computer: &mut Computer,
+  mut display: impl FnMut(&str),
+  mut next_line: impl FnMut() -> Option<String>,
+  transcript: &mut Transcript,
+) -> Result<RunState> {
+  loop {
+    let state = computer.run_until_blocked()?;
+    let text = computer.drain_text();
+    if !text.is_empty() {
+      display(&text);
+      transcript.entries.push(Entry::Output(text));
+    }
+    if state != RunState::NeedsInput {
+      return Ok(state);
+    }
+    match next_line() {
+      Some(line) => {
+        computer.push_line(&line);
+        transcript.entries.push(Entry::Input(line));
+      },
+      None => return Ok(state),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::assembler::assemble;
+
+  // Prompts, echoes lines back until it sees "!", then prints 1000
+  fn echo() -> Computer {
+    assemble("
+      prompt: OUTPUT #63
+              OUTPUT #10
+      loop:   INPUT -> [c]
+              EQUALS [c], #33 -> [done]
+              JUMPIFTRUE [done], #end
+              OUTPUT [c]
+              JUMPIFTRUE #1, #loop
+      end:    OUTPUT #1000
+              HALT
+      c:      DATA 0
+      done:   DATA 0
+    ").unwrap().into()
+  }
+
+  #[test]
+  fn text_io() {
+    let mut computer = echo();
+    computer.push_line("hi");
+    computer.push_str("!");
+    computer.run_until_blocked().unwrap();
+    assert_eq!(
+      vec![Ascii::Text("?\nhi\n".to_string()), Ascii::Value(1000)],
+      computer.drain_ascii()
+    );
+
+    let mut computer = echo();
+    computer.push_str("ok!");
+    computer.run_until_blocked().unwrap();
+    assert_eq!("?\nok1000", computer.drain_text());
+    assert!(computer.output.is_empty());
+  }
+
+  #[test]
+  fn records_and_replays() {
+    let mut inputs = vec!["one".to_string(), "two!".to_string()].into_iter();
+    let mut shown = String::new();
+    let mut transcript = Transcript::default();
+    let state = interact(&mut echo(), |text| shown += text, || inputs.next(), &mut transcript).unwrap();
+    assert_eq!(RunState::Halted, state);
+    assert_eq!("?\none\ntwo1000", shown);
+    assert_eq!("?\n> one\none\n> two!\ntwo1000\n", transcript.to_string());
+
+    let mut replay = Transcript::inputs(&transcript.to_string()).into_iter();
+    let mut replayed = Transcript::default();
+    interact(&mut echo(), |_| {}, || replay.next(), &mut replayed).unwrap();
+    assert_eq!(transcript, replayed);
+  }
+
+  #[test]
+  fn stops_when_input_runs_out() {
+    let mut transcript = Transcript::default();
+    let state = interact(&mut echo(), |_| {}, || None, &mut transcript).unwrap();
+    assert_eq!(RunState::NeedsInput, state);
+    assert_eq!("?\n", transcript.to_string());
+  }
+}
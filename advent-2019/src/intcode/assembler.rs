@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use advent_shared::{parsers::Delimiter, ParseError};
+
+use super::Value;
+
+/// Mnemonic, opcode, number of operands read, and whether it also writes.
+const MNEMONICS: &[(&str, Value, usize, bool)] = &[
+  ("ADD", 1, 2, true),
+  ("MULT", 2, 2, true),
+  ("INPUT", 3, 0, true),
+  ("OUTPUT", 4, 1, false),
+  ("JUMPIFTRUE", 5, 2, false),
+  ("JUMPIFFALSE", 6, 2, false),
+  ("LESSTHAN", 7, 2, true),
+  ("EQUALS", 8, 2, true),
+  ("ADJUSTRELATIVEBASE", 9, 1, false),
+  ("HALT", 99, 0, false),
+];
+
+/// A piece of a line, along with the byte offset it starts at.
+type Span<'a> = (usize, &'a str);
+
+/// A number, or a label plus an optional `+N`/`-N`.
+struct Expr<'a> {
+  label: Option<Span<'a>>,
+  offset: Value,
+}
+
+struct Operand<'a> {
+  mode: Value,
+  expr: Expr<'a>,
+}
+
+enum Statement<'a> {
+  Instruction { opcode: Value, operands: Vec<Operand<'a>> },
+  Data(Vec<Expr<'a>>),
+}
+
+impl Statement<'_> {
+  fn size(&self) -> usize {
+    match self {
+      Statement::Instruction { operands, .. } => 1 + operands.len(),
+      Statement::Data(values) => values.len(),
+    }
+  }
+}
+
+/// Assembles the same language the disassembler prints, one statement per line:
+///
+/// ```text
+/// start: INPUT -> [x]      ; labels end in ':', comments start with ';'
+///        MULT [x], #2 -> [rb+1]
+///        OUTPUT [rb+1]
+///        JUMPIFTRUE #1, #start
+/// x:     DATA 0
+/// ```
+///
+/// Operands are `#value` (immediate), `[value]` (position) or `[rb+N]`
+/// (relative), where a value is a number, a label, or a label plus or minus a
+/// number. Mnemonics are case-insensitive.
+pub fn assemble(source: &str) -> Result<Vec<Value>, ParseError> {
+  let mut labels = HashMap::new();
+  let mut statements = vec![];
+  let mut address = 0;
+  for (i, line) in source.lines().enumerate() {
+    let (label, statement) = parse_line(line).map_err(|e| e.on_line(i + 1))?;
+    if let Some((offset, name)) = label {
+      if labels.insert(name, address).is_some() {
+        return Err(ParseError::at(line, offset, format!("label {} is already defined", name)).on_line(i + 1));
+      }
+    }
+    if let Some(statement) = statement {
+      address += statement.size();
+      statements.push((i + 1, line, statement));
+    }
+  }
+
+  let resolve = |line: &str, expr: &Expr| match expr.label {
+    Some((offset, name)) => match labels.get(name) {
+      Some(&address) => (address as Value)
+        .checked_add(expr.offset)
+        .ok_or_else(|| ParseError::at(line, offset, format!("{}{:+} is out of range", name, expr.offset))),
+      None => Err(ParseError::at(line, offset, format!("unknown label {}", name))),
+    },
+    None => Ok(expr.offset),
+  };
+  let mut program = vec![];
+  for (number, line, statement) in statements {
+    let on_line = |e: ParseError| e.on_line(number);
+    match statement {
+      Statement::Instruction { opcode, operands } => {
+        let modes: Value = operands.iter().zip([100, 1000, 10000].iter()).map(|(o, place)| o.mode * place).sum();
+        program.push(opcode + modes);
+        for operand in &operands {
+          program.push(resolve(line, &operand.expr).map_err(on_line)?);
+        }
+      },
+      Statement::Data(values) => {
+        for value in &values {
+          program.push(resolve(line, value).map_err(on_line)?);
+        }
+      },
+    }
+  }
+  Ok(program)
+}
+
+fn parse_line(line: &str) -> Result<(Option<Span<'_>>, Option<Statement<'_>>), ParseError> {
+  let code = &line[..line.find(';').unwrap_or(line.len())];
+  let mut rest = (0, code);
+  let mut label = None;
+  if let Some(colon) = code.find(':') {
+    let name = trim((0, &code[..colon]));
+    if !is_identifier(name.1) {
+      return Err(ParseError::at(line, name.0, format!("invalid label {:?}", name.1)));
+    }
+    label = Some(name);
+    rest = (colon + 1, &code[colon + 1..]);
+  }
+  let (offset, text) = trim(rest);
+  if text.is_empty() {
+    return Ok((label, None));
+  }
+
+  let end = text.find(char::is_whitespace).unwrap_or(text.len());
+  let mnemonic = text[..end].to_ascii_uppercase();
+  let args = (offset + end, &text[end..]);
+  if mnemonic == "DATA" {
+    let values = split(args, ",")?.into_iter().map(|value| parse_expr(line, value)).collect::<Result<_, _>>()?;
+    return Ok((label, Some(Statement::Data(values))));
+  }
+  let &(_, opcode, reads, writes) = MNEMONICS.iter().find(|(m, ..)| *m == mnemonic)
+    .ok_or_else(|| ParseError::at(line, offset, format!("unknown mnemonic {}", &text[..end])))?;
+
+  let (sources, store) = match args.1.find("->") {
+    Some(arrow) => ((args.0, &args.1[..arrow]), Some((args.0 + arrow + 2, &args.1[arrow + 2..]))),
+    None => (args, None),
+  };
+  let mut operands = vec![];
+  if !trim(sources).1.is_empty() {
+    for source in split(sources, ",")? {
+      operands.push(parse_operand(line, source)?);
+    }
+  }
+  let count = match reads {
+    1 => "1 operand".to_string(),
+    n => format!("{} operands", n),
+  };
+  let usage = match (reads, writes) {
+    (0, true) => format!("{} -> target", mnemonic),
+    (_, true) => format!("{} {} -> target", mnemonic, count),
+    (0, false) => format!("{} with no operands", mnemonic),
+    (_, false) => format!("{} with {}", mnemonic, count),
+  };
+  if operands.len() != reads || store.is_some() != writes {
+    return Err(ParseError::at(line, offset, format!("expected {}", usage)));
+  }
+  if let Some(store) = store {
+    let target = parse_operand(line, store)?;
+    if target.mode == 1 {
+      return Err(ParseError::at(line, trim(store).0, "can't write to an immediate"));
+    }
+    operands.push(target);
+  }
+  Ok((label, Some(Statement::Instruction { opcode, operands })))
+}
+
+fn parse_operand<'a>(line: &'a str, operand: Span<'a>) -> Result<Operand<'a>, ParseError> {
+  let (offset, text) = trim(operand);
+  if let Some(value) = text.strip_prefix('#') {
+    return Ok(Operand { mode: 1, expr: parse_expr(line, (offset + 1, value))? });
+  }
+  let inner = match text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+    Some(inner) => trim((offset + 1, inner)),
+    None => return Err(ParseError::at(line, offset, "expected an operand like #1, [2] or [rb+3]")),
+  };
+  let relative = inner.1.strip_prefix("rb").filter(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'));
+  match relative.map(str::trim_start) {
+    Some("") => Ok(Operand { mode: 2, expr: Expr { label: None, offset: 0 } }),
+    Some(rest) => {
+      let start = inner.0 + inner.1.len() - rest.len();
+      let expr = match rest.strip_prefix('+') {
+        Some(rest) => (start + 1, rest),
+        None => (start, rest),
+      };
+      Ok(Operand { mode: 2, expr: parse_expr(line, expr)? })
+    },
+    None => Ok(Operand { mode: 0, expr: parse_expr(line, inner)? }),
+  }
+}
+
+fn parse_expr<'a>(line: &'a str, expr: Span<'a>) -> Result<Expr<'a>, ParseError> {
+  let (offset, text) = trim(expr);
+  if let Ok(value) = text.parse::<Value>() {
+    return Ok(Expr { label: None, offset: value });
+  }
+  let end = text.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(text.len());
+  if !is_identifier(&text[..end]) {
+    return Err(ParseError::at(line, offset, format!("expected a number or label, found {:?}", text)));
+  }
+  let label = Some((offset, &text[..end]));
+  let (rest_offset, rest) = trim((offset + end, &text[end..]));
+  let value = if rest.is_empty() {
+    Some(0)
+  } else if let Some(number) = rest.strip_prefix('+') {
+    number.trim_start().parse::<Value>().ok()
+  } else if let Some(number) = rest.strip_prefix('-') {
+    number.trim_start().parse::<Value>().ok().map(|n| -n)
+  } else {
+    None
+  };
+  value
+    .map(|offset| Expr { label, offset })
+    .ok_or_else(|| ParseError::at(line, rest_offset, format!("expected +N or -N after label, found {:?}", rest)))
+}
+
+fn is_identifier(s: &str) -> bool {
+  s != "rb"
+    && s.starts_with(|c: char| c.is_alphabetic() || c == '_')
+    && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn trim((offset, s): Span<'_>) -> Span<'_> {
+  let trimmed = s.trim_start();
+  (offset + s.len() - trimmed.len(), trimmed.trim_end())
+}
+
+fn split<'a>((offset, s): Span<'a>, delimiter: &str) -> Result<Vec<Span<'a>>, ParseError> {
+  Ok(Delimiter::Str(delimiter).split(s)?.into_iter().map(|(start, field)| (offset + start, field)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::{disassembler::disassemble, Computer};
+
+  #[test]
+  fn encodes_modes() {
+    assert_eq!(Ok(vec![1001, 12, 5, 40]), assemble("ADD [12], #5 -> [40]"));
+    assert_eq!(Ok(vec![22202, 3, -2, 0]), assemble("mult [rb+3], [rb-2] -> [rb]"));
+    assert_eq!(Ok(vec![203, 1, 104, 7, 1006, 3, 0, 99]),
+      assemble("INPUT -> [rb + 1]\nOUTPUT #7\nJUMPIFFALSE [3], #0\nHALT"));
+  }
+
+  #[test]
+  fn labels_and_data() {
+    let source = "
+      ; doubles every input until it sees a 0
+      start: INPUT -> [x]
+             JUMPIFFALSE [x], #end
+             MULT [x], #2 -> [x]
+             OUTPUT [x]
+             JUMPIFTRUE #1, #start
+      end:   HALT
+      x:     DATA 0
+      table: DATA 1, x, x+1, start-1
+    ";
+    let program = assemble(source).unwrap();
+    assert_eq!(vec![3, 15, 1006, 15, 14, 1002, 15, 2, 15, 4, 15, 1105, 1, 0, 99, 0, 1, 15, 16, -1], program);
+
+    let mut computer: Computer = program.into();
+    computer.input.extend(vec![3, 5, 0]);
+    computer.run_until_blocked().unwrap();
+    assert_eq!(vec![6, 10], computer.output.into_iter().collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn round_trips_disassembly() {
+    let image = vec![3,225,1,225,6,6,1101,1,238,225,104,0,1101,90,64,225,21101,-3,4,0,109,5,99];
+    let source = disassemble(&image).iter().map(|line| line.to_string()[32..].to_string()).collect::<Vec<_>>().join("\n");
+    assert_eq!(Ok(image), assemble(&source));
+  }
+
+  #[test]
+  fn errors_point_at_the_source() {
+    let error = |source| assemble(source).unwrap_err().to_string();
+    assert_eq!("line 2, column 1: unknown mnemonic JMP", error("HALT\nJMP #1"));
+    assert_eq!("line 1, column 15: can't write to an immediate", error("ADD #1, #2 -> #3"));
+    assert_eq!("line 1, column 1: expected ADD 2 operands -> target", error("ADD #1 -> [3]"));
+    assert_eq!("line 1, column 1: expected OUTPUT with 1 operand", error("OUTPUT #1 -> [2]"));
+    assert_eq!("line 1, column 1: expected HALT with no operands", error("HALT #1"));
+    assert_eq!("line 3, column 9: unknown label nowhere", error("a: HALT\n\nOUTPUT [nowhere]"));
+    assert_eq!("line 2, column 1: label a is already defined", error("a: HALT\na: HALT"));
+    assert_eq!("line 1, column 8: expected an operand like #1, [2] or [rb+3]", error("OUTPUT 5"));
+    assert_eq!("line 1, column 12: expected +N or -N after label, found \"* 2\"", error("DATA label * 2\nlabel: HALT"));
+    assert_eq!(
+      "line 2, column 6: x+9223372036854775807 is out of range",
+      error("HALT\nDATA x+9223372036854775807\nx: HALT")
+    );
+  }
+}
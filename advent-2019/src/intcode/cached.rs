@@ -0,0 +1,327 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use super::{Computer, IntcodeError, Opcode, Parameter, Position, RunState, Tracer, Value, Word};
+
+/// A `Computer` that decodes each instruction in its image once and reuses
+/// it, rather than decoding again every time it's executed.
+///
+/// Arithmetic, comparisons and jumps run straight from the decoded copy, with
+/// position mode operands already turned into addresses. Everything else, and
+/// anything that would fail, is handed to the plain interpreter to execute, as
+/// is every instruction when the machine is strict, has a step limit or is
+/// keeping history. So it fails, blocks and halts exactly as the interpreter
+/// would.
+///
+/// The decoded program is shared between clones, so cloning costs little more
+/// than cloning the plain machine. A write anywhere inside a decoded
+/// instruction makes this machine decode it afresh from then on, so
+/// self-modifying programs behave as they do on the interpreter too. Memory
+/// has to be changed through `write` to keep that honest, which is why the
+/// computer isn't exposed mutably.
+#[derive(Clone, Debug)]
+pub struct CachedComputer<W = Value> {
+  computer: Computer<W>,
+  decoded: Arc<[Decoded<W>]>,
+  /// Addresses whose decoded instruction has been written over.
+  stale: Vec<bool>,
+}
+
+/// An operand, resolved as far as it can be without running anything.
+#[derive(Clone, Debug)]
+enum Operand<W> {
+  Value(W),
+  At(Position),
+  Relative(W),
+}
+
+#[derive(Clone, Debug)]
+enum Decoded<W> {
+  Add(Operand<W>, Operand<W>, Operand<W>),
+  Mult(Operand<W>, Operand<W>, Operand<W>),
+  LessThan(Operand<W>, Operand<W>, Operand<W>),
+  Equals(Operand<W>, Operand<W>, Operand<W>),
+  /// Jumps to the second operand if the first is non-zero, or with `false`,
+  /// if it's zero.
+  Jump(bool, Operand<W>, Operand<W>),
+  AdjustRelativeBase(Operand<W>),
+  /// Input, output, halting, anything with an operand that can never be
+  /// valid, and anything that doesn't decode at all.
+  Interpret,
+}
+
+/// The longest instruction, and so how far back a write can reach into one.
+const MAX_SIZE: Position = 4;
+
+struct Invalidate<'a>(&'a mut [bool]);
+
+impl Tracer for Invalidate<'_> {
+  fn write<W: Word>(&mut self, address: Position, _old: &W, _new: &W) {
+    invalidate(self.0, address);
+  }
+}
+
+fn invalidate(stale: &mut [bool], address: Position) {
+  let end = (address + 1).min(stale.len());
+  let start = (address + 1).saturating_sub(MAX_SIZE).min(end);
+  stale[start..end].fill(true);
+}
+
+fn operand<W: Word>(param: Parameter<W>) -> Option<Operand<W>> {
+  match param {
+    Parameter::Immediate(value) => Some(Operand::Value(value)),
+    Parameter::Position(address) => position(&address).map(Operand::At),
+    Parameter::Relative(offset) => Some(Operand::Relative(offset)),
+  }
+}
+
+impl<W: Word> Decoded<W> {
+  fn new(opcode: Opcode<W>) -> Option<Decoded<W>> {
+    use Opcode::*;
+    Some(match opcode {
+      Add { left, right, store } => Decoded::Add(operand(left)?, operand(right)?, operand(store)?),
+      Mult { left, right, store } => Decoded::Mult(operand(left)?, operand(right)?, operand(store)?),
+      LessThan { left, right, store } => Decoded::LessThan(operand(left)?, operand(right)?, operand(store)?),
+      Equals { left, right, store } => Decoded::Equals(operand(left)?, operand(right)?, operand(store)?),
+      JumpIfTrue { source, dest } => Decoded::Jump(true, operand(source)?, operand(dest)?),
+      JumpIfFalse { source, dest } => Decoded::Jump(false, operand(source)?, operand(dest)?),
+      AdjustRelativeBase { offset } => Decoded::AdjustRelativeBase(operand(offset)?),
+      Input { .. } | Output { .. } | Halt => return None,
+    })
+  }
+}
+
+impl<W: Word> CachedComputer<W> {
+  /// Decodes every address in the program image up front. Instructions that
+  /// run off the end of the image aren't kept, since writes out there aren't
+  /// watched.
+  pub fn new(mut computer: Computer<W>) -> Self {
+    let ip = computer.instruction_pointer;
+    let len = computer.memory.image().len();
+    let decoded = (0..len).map(|address| {
+      computer.instruction_pointer = address;
+      computer.next_opcode().ok()
+        .filter(|opcode| address + opcode.size() <= len)
+        .and_then(Decoded::new)
+        .unwrap_or(Decoded::Interpret)
+    }).collect();
+    computer.instruction_pointer = ip;
+    CachedComputer { computer, decoded, stale: vec![false; len] }
+  }
+
+  pub fn computer(&self) -> &Computer<W> {
+    &self.computer
+  }
+
+  pub fn into_inner(self) -> Computer<W> {
+    self.computer
+  }
+
+  pub fn write(&mut self, address: Position, value: W) {
+    self.computer.write(address, value);
+    invalidate(&mut self.stale, address);
+  }
+
+  pub fn push_input(&mut self, value: W) {
+    self.computer.input.push_back(value);
+  }
+
+  pub fn step(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    if self.execute() {
+      return Ok(RunState::Running);
+    }
+    let opcode = self.computer.next_opcode()?;
+    self.computer.execute_with(opcode, &mut Invalidate(&mut self.stale))
+  }
+
+  /// Executes the next instruction from its decoded copy, or returns false,
+  /// having touched nothing, if it has to be left to the interpreter.
+  fn execute(&mut self) -> bool {
+    let CachedComputer { computer, decoded, stale } = self;
+    let ip = computer.instruction_pointer;
+    if computer.strict || computer.step_limit.is_some() || computer.history.is_some() {
+      return false;
+    }
+    match decoded.get(ip) {
+      Some(decoded) if !stale[ip] => run_decoded(computer, stale, decoded).is_some(),
+      _ => false,
+    }
+  }
+
+  pub fn run(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    loop {
+      match self.step()? {
+        RunState::Running => {},
+        state => return Ok(state),
+      }
+    }
+  }
+
+  pub fn run_until_blocked(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    loop {
+      match self.run()? {
+        RunState::Output(_) => {},
+        state => return Ok(state),
+      }
+    }
+  }
+}
+
+/// Executes a decoded instruction, or leaves everything untouched and returns
+/// `None` if the interpreter has to.
+fn run_decoded<W: Word>(computer: &mut Computer<W>, stale: &mut [bool], decoded: &Decoded<W>) -> Option<()> {
+  let value = |operand: &Operand<W>| match operand {
+    Operand::Value(value) => Some(value.clone()),
+    _ => Some(computer.memory[address(computer, operand)?].clone()),
+  };
+  let ip = computer.instruction_pointer;
+  let (written, next) = match decoded {
+    Decoded::Add(left, right, store) => {
+      let value = value(left)?.checked_add(&value(right)?)?;
+      (Some((address(computer, store)?, value)), ip + 4)
+    },
+    Decoded::Mult(left, right, store) => {
+      let value = value(left)?.checked_mul(&value(right)?)?;
+      (Some((address(computer, store)?, value)), ip + 4)
+    },
+    Decoded::LessThan(left, right, store) => {
+      let value = W::from_i64((value(left)? < value(right)?) as i64);
+      (Some((address(computer, store)?, value)), ip + 4)
+    },
+    Decoded::Equals(left, right, store) => {
+      let value = W::from_i64((value(left)? == value(right)?) as i64);
+      (Some((address(computer, store)?, value)), ip + 4)
+    },
+    Decoded::Jump(if_true, source, dest) => {
+      let jump = value(source)?.is_zero() != *if_true;
+      let dest = position(&value(dest)?)?;
+      (None, if jump { dest } else { ip + 3 })
+    },
+    Decoded::AdjustRelativeBase(offset) => {
+      let base = computer.relative_base.checked_add(&value(offset)?)?;
+      computer.relative_base = base;
+      (None, ip + 2)
+    },
+    Decoded::Interpret => return None,
+  };
+  if let Some((address, value)) = written {
+    computer.write(address, value);
+    invalidate(stale, address);
+  }
+  computer.instruction_pointer = next;
+  computer.steps += 1;
+  Some(())
+}
+
+fn address<W: Word>(computer: &Computer<W>, operand: &Operand<W>) -> Option<Position> {
+  match operand {
+    Operand::Value(address) => position(address),
+    Operand::At(address) => Some(*address),
+    Operand::Relative(offset) => position(&computer.relative_base.checked_add(offset)?),
+  }
+}
+
+fn position<W: Word>(address: &W) -> Option<Position> {
+  address.to_i64().and_then(|a| Position::try_from(a).ok())
+}
+
+#[cfg(test)]
+mod tests {
+  use num::BigInt;
+  use super::*;
+  use crate::intcode::assembler::assemble;
+
+  /// Runs `program` on both engines in lockstep, checking they agree after
+  /// every single step, including on how it fails. Returns the output.
+  fn check_against_interpreter<W: Word>(mut plain: Computer<W>, input: &[W]) -> Vec<W> {
+    plain.input.extend(input.iter().cloned());
+    let mut cached = CachedComputer::new(plain.clone());
+    loop {
+      let expected = plain.step();
+      assert_eq!(expected, cached.step());
+      let computer = cached.computer();
+      assert_eq!(plain.instruction_pointer, computer.instruction_pointer);
+      assert_eq!(plain.relative_base, computer.relative_base);
+      assert_eq!(plain.memory, computer.memory);
+      assert_eq!(plain.steps, computer.steps);
+      match expected {
+        Ok(RunState::Running) | Ok(RunState::Output(_)) => {},
+        _ => break,
+      }
+    }
+    cached.into_inner().output.into_iter().collect()
+  }
+
+  fn words(program: &[Value]) -> Computer {
+    program.to_vec().into()
+  }
+
+  #[test]
+  fn matches_interpreter() {
+    let day5 = [
+      3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,
+      1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,
+      999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99
+    ];
+    for &(input, output) in [(7, 999), (8, 1000), (9, 1001)].iter() {
+      assert_eq!(vec![output], check_against_interpreter(words(&day5), &[input]));
+    }
+    let quine = [109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+    assert_eq!(quine.to_vec(), check_against_interpreter(words(&quine), &[]));
+    assert!(check_against_interpreter(words(&[1,9,10,3,2,3,11,0,99,30,40,50]), &[]).is_empty());
+
+    // Runs just the same on other word types
+    let big: Computer<BigInt> = quine.iter().map(|&v| BigInt::from(v)).collect::<Vec<_>>().into();
+    assert_eq!(16, check_against_interpreter(big, &[]).len());
+  }
+
+  #[test]
+  fn self_modifying_code() {
+    // Rewrites the immediate in its own LESSTHAN each time round the loop,
+    // and finally overwrites the JUMPIFTRUE with a HALT from the inside
+    let program = assemble("
+      loop:  ADD [check+1], #1 -> [check+1]
+             OUTPUT [check+1]
+      check: LESSTHAN #-3, #0 -> [flag]
+             JUMPIFTRUE [flag], #loop
+             ADD #99, #0 -> [next]
+      next:  JUMPIFTRUE #1, #loop
+      flag:  DATA 0
+    ").unwrap();
+    assert_eq!(vec![-2, -1, 0], check_against_interpreter(program.into(), &[]));
+
+    // An instruction finished off past the end of the image
+    check_against_interpreter(words(&[1101, 1, 1, 4, 1101, 99]), &[]);
+  }
+
+  #[test]
+  fn faults_match_the_interpreter() {
+    // A negative address, an overflow, a jump to nowhere, an unknown opcode
+    check_against_interpreter(words(&[1101, 1, 2, 7, 1, -1, 0, 0, 99]), &[]);
+    check_against_interpreter(words(&[1102, Value::MAX, 2, 0, 99]), &[]);
+    check_against_interpreter(words(&[109, -3, 1106, 0, -1]), &[]);
+    check_against_interpreter(words(&[1105, 1, 3, 42]), &[]);
+    check_against_interpreter(words(&[3, 3, 99]), &[]);
+
+    // Strict machines, ones with a step limit and ones keeping history are
+    // left to the interpreter
+    check_against_interpreter(words(&[1101, 1, 2, 100, 99]).strict(), &[]);
+    check_against_interpreter(words(&[11101, 1, 2, 1, 99]).strict(), &[]);
+    check_against_interpreter(words(&[1105, 1, 0]).with_step_limit(10), &[]);
+    let mut computer = CachedComputer::new(words(&[1001, 5, 1, 5, 99, 41]).with_history(10));
+    computer.run_until_blocked().unwrap();
+    let mut computer = computer.into_inner();
+    assert_eq!(42, computer.memory[5]);
+    while computer.step_back() {}
+    assert_eq!((0, 41), (computer.instruction_pointer, computer.memory[5]));
+  }
+
+  #[test]
+  fn writes_invalidate() {
+    let mut computer = CachedComputer::new(words(&[1, 0, 0, 0, 99]));
+    computer.write(1, 4);
+    computer.write(2, 4);
+    computer.run_until_blocked().unwrap();
+    assert_eq!(198, computer.computer().memory[0]);
+  }
+}
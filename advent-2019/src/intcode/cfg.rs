@@ -0,0 +1,304 @@
+//! Static control flow analysis of a memory image. Everything reachable from
+//! address 0 is split into basic blocks, following jumps whose targets are
+//! immediates. Intcode has no call instruction, so a jump is taken to be a call
+//! when the instructions just before it store the address following the jump,
+//! and a jump through the relative base is taken to be a return.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::disassembler::{disassemble_from, Line};
+use super::{Opcode, Parameter, Position, Value};
+
+/// How control leaves a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+  /// Runs straight on into the block starting at this address.
+  Fallthrough(Position),
+  Jump(Position),
+  Branch { taken: Position, not_taken: Position },
+  Call { target: Position, returns_to: Position },
+  /// Jumps through the relative base, most likely to a stored return address.
+  Return,
+  /// Jumps to a target read from memory, carrying on at `not_taken` if the
+  /// jump is conditional.
+  Indirect { not_taken: Option<Position> },
+  /// Sometimes jumps to an immediate target outside the image, carrying on at
+  /// `not_taken` otherwise.
+  InvalidBranch { not_taken: Position },
+  Halt,
+  /// Runs into something that doesn't decode, off the end of the image, or
+  /// always jumps to an immediate target outside it.
+  Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+  Fallthrough,
+  Jump,
+  Taken,
+  NotTaken,
+  Call,
+  /// From a call to the instruction after it, where the callee returns to.
+  AfterCall,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+  pub start: Position,
+  /// The block's instructions, ending with a data line if it hit `Exit::Invalid`.
+  pub lines: Vec<Line>,
+  pub exit: Exit,
+}
+
+impl Exit {
+  pub fn successors(self) -> Vec<(Position, Edge)> {
+    match self {
+      Exit::Fallthrough(next) => vec![(next, Edge::Fallthrough)],
+      Exit::Jump(target) => vec![(target, Edge::Jump)],
+      Exit::Branch { taken, not_taken } => vec![(taken, Edge::Taken), (not_taken, Edge::NotTaken)],
+      Exit::Call { target, returns_to } => vec![(target, Edge::Call), (returns_to, Edge::AfterCall)],
+      Exit::Indirect { not_taken: Some(next) } | Exit::InvalidBranch { not_taken: next } => {
+        vec![(next, Edge::NotTaken)]
+      },
+      Exit::Return | Exit::Indirect { not_taken: None } | Exit::Halt | Exit::Invalid => vec![],
+    }
+  }
+}
+
+impl Block {
+  /// Where control can go from the end of this block. An address just past the
+  /// end of the image has no block of its own.
+  pub fn successors(&self) -> Vec<(Position, Edge)> {
+    self.exit.successors()
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cfg {
+  pub blocks: BTreeMap<Position, Block>,
+}
+
+impl Cfg {
+  pub fn build(image: &[Value]) -> Cfg {
+    let mut lines = BTreeMap::new();
+    let mut flows = BTreeMap::new();
+    let mut leaders = BTreeSet::new();
+    let mut pending = vec![0];
+    let in_image = |target: Value| (0..image.len() as Value).contains(&target);
+
+    // Trace each straight line of code from a jump target or return site,
+    // remembering the constants it stores in case it ends in a call
+    while let Some(start) = pending.pop() {
+      leaders.insert(start);
+      let mut address = start;
+      let mut stored = vec![];
+      loop {
+        if lines.contains_key(&address) {
+          // Ran into code that's already been traced, which now needs a block of its own
+          leaders.insert(address);
+          break;
+        }
+        let line = match disassemble_from(image, address, 1).pop() {
+          Some(line) => line,
+          // Off the end of the image
+          None => break,
+        };
+        let next = address + line.words.len();
+        // `None` if execution just carries on to `next`
+        let exit = match line.opcode {
+          None => Some(Exit::Invalid),
+          Some(Opcode::Halt) => Some(Exit::Halt),
+          Some(Opcode::JumpIfTrue { source, dest }) | Some(Opcode::JumpIfFalse { source, dest }) => {
+            let jump_if = matches!(line.opcode, Some(Opcode::JumpIfTrue { .. }));
+            let always = match source {
+              Parameter::Immediate(value) => Some((value != 0) == jump_if),
+              _ => None,
+            };
+            match (always, dest) {
+              (Some(false), _) => None,
+              (Some(true), Parameter::Immediate(target)) if in_image(target) && stored.contains(&(next as Value)) => {
+                Some(Exit::Call { target: target as Position, returns_to: next })
+              },
+              (Some(true), Parameter::Immediate(target)) if in_image(target) => Some(Exit::Jump(target as Position)),
+              (Some(true), Parameter::Immediate(_)) => Some(Exit::Invalid),
+              (None, Parameter::Immediate(target)) if in_image(target) => {
+                Some(Exit::Branch { taken: target as Position, not_taken: next })
+              },
+              (None, Parameter::Immediate(_)) => Some(Exit::InvalidBranch { not_taken: next }),
+              (Some(true), Parameter::Relative(_)) => Some(Exit::Return),
+              (Some(true), _) => Some(Exit::Indirect { not_taken: None }),
+              (None, _) => Some(Exit::Indirect { not_taken: Some(next) }),
+            }
+          },
+          Some(Opcode::Add { left: Parameter::Immediate(a), right: Parameter::Immediate(b), .. }) => {
+            // A sum that overflows can't be a return address, so isn't worth keeping
+            stored.extend(a.checked_add(b));
+            None
+          },
+          Some(Opcode::Mult { left: Parameter::Immediate(a), right: Parameter::Immediate(b), .. }) => {
+            stored.extend(a.checked_mul(b));
+            None
+          },
+          Some(_) => None,
+        };
+        lines.insert(address, line);
+        match exit {
+          None => address = next,
+          Some(exit) => {
+            // Carrying on past the last instruction leads nowhere worth a block
+            for (target, _) in exit.successors().into_iter().filter(|&(target, _)| target < image.len()) {
+              leaders.insert(target);
+              pending.push(target);
+            }
+            flows.insert(address, exit);
+            break;
+          },
+        }
+      }
+    }
+
+    // Then cut the code up at every leader
+    let mut blocks = BTreeMap::new();
+    for &start in &leaders {
+      let mut block = Block { start, lines: vec![], exit: Exit::Invalid };
+      let mut address = start;
+      while let Some(line) = lines.get(&address) {
+        block.lines.push(line.clone());
+        if let Some(exit) = flows.get(&address) {
+          block.exit = *exit;
+          break;
+        }
+        address += line.words.len();
+        if leaders.contains(&address) {
+          block.exit = Exit::Fallthrough(address);
+          break;
+        }
+      }
+      blocks.insert(start, block);
+    }
+    Cfg { blocks }
+  }
+
+  /// The graph in Graphviz's DOT language, with each block labelled with its
+  /// disassembly.
+  pub fn to_dot(&self) -> String {
+    let mut dot = String::from("digraph cfg {\n  node [shape=box, fontname=\"monospace\"];\n");
+    for block in self.blocks.values() {
+      let mut label: String = block.lines.iter().map(|line| format!("{}\\l", escape(&line.to_string()))).collect();
+      match block.exit {
+        Exit::Return => label += "(return)\\l",
+        Exit::Indirect { .. } => label += "(indirect jump)\\l",
+        Exit::InvalidBranch { .. } => label += "(branch out of image)\\l",
+        _ => {},
+      }
+      writeln!(dot, "  b{} [label=\"{}\"];", block.start, label).unwrap();
+    }
+    for block in self.blocks.values() {
+      for (target, edge) in block.successors().into_iter().filter(|(target, _)| self.blocks.contains_key(target)) {
+        let style = match edge {
+          Edge::Fallthrough | Edge::Jump => "",
+          Edge::Taken => " [label=\"taken\", color=green]",
+          Edge::NotTaken => " [label=\"not taken\", color=red]",
+          Edge::Call => " [label=\"call\", color=blue]",
+          Edge::AfterCall => " [style=dashed]",
+        };
+        writeln!(dot, "  b{} -> b{}{};", block.start, target, style).unwrap();
+      }
+    }
+    dot += "}\n";
+    dot
+  }
+}
+
+fn escape(text: &str) -> String {
+  text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::{assembler::assemble, Computer};
+
+  #[test]
+  fn calls_and_returns() {
+    let program = assemble("
+              ADJUSTRELATIVEBASE #stack
+              ADD #back, #0 -> [rb+0]
+              JUMPIFTRUE #1, #double
+      back:   OUTPUT [x]
+              JUMPIFFALSE [x], #0
+              HALT
+      double: MULT [x], #2 -> [x]
+              JUMPIFTRUE #1, [rb+0]
+      x:      DATA 21
+      stack:  DATA 0
+    ").unwrap();
+    let mut computer: Computer = program.clone().into();
+    computer.run_until_blocked().unwrap();
+    assert_eq!(Some(42), computer.output.pop_front());
+
+    let cfg = Cfg::build(&program);
+    assert_eq!(vec![0, 9, 14, 15], cfg.blocks.keys().copied().collect::<Vec<_>>());
+    assert_eq!(Exit::Call { target: 15, returns_to: 9 }, cfg.blocks[&0].exit);
+    assert_eq!(3, cfg.blocks[&0].lines.len());
+    assert_eq!(Exit::Branch { taken: 0, not_taken: 14 }, cfg.blocks[&9].exit);
+    assert_eq!(Exit::Halt, cfg.blocks[&14].exit);
+    assert_eq!(Exit::Return, cfg.blocks[&15].exit);
+
+    let dot = cfg.to_dot();
+    assert!(dot.starts_with("digraph cfg {"));
+    assert!(dot.contains("  b0 -> b15 [label=\"call\", color=blue];\n"));
+    assert!(dot.contains("  b0 -> b9 [style=dashed];\n"));
+    assert!(dot.contains("MULT [22], #2 -> [22]\\l"));
+    assert!(dot.contains("(return)\\l\"];"));
+  }
+
+  #[test]
+  fn splits_blocks_at_jump_targets() {
+    let program = assemble("
+            JUMPIFTRUE [flag], #mid
+            OUTPUT #1
+      mid:  OUTPUT #2
+            JUMPIFTRUE #1, [target]
+      flag: DATA 1
+      target: DATA 0
+    ").unwrap();
+    let cfg = Cfg::build(&program);
+    assert_eq!(vec![0, 3, 5], cfg.blocks.keys().copied().collect::<Vec<_>>());
+    assert_eq!(Exit::Branch { taken: 5, not_taken: 3 }, cfg.blocks[&0].exit);
+    assert_eq!(Exit::Fallthrough(5), cfg.blocks[&3].exit);
+    assert_eq!(Exit::Indirect { not_taken: None }, cfg.blocks[&5].exit);
+    assert_eq!(2, cfg.blocks[&5].lines.len());
+
+    // Code that never decodes ends its block
+    let cfg = Cfg::build(&[1101, 1, 2, 3, 42]);
+    assert_eq!(Exit::Invalid, cfg.blocks[&0].exit);
+    assert_eq!(None, cfg.blocks[&0].lines[1].opcode);
+
+    // As does a jump that always leaves the image
+    let cfg = Cfg::build(&[1105, 1, 99, 99]);
+    assert_eq!(Exit::Invalid, cfg.blocks[&0].exit);
+    assert_eq!(1, cfg.blocks.len());
+
+    // A conditional one still carries on when it isn't taken
+    let cfg = Cfg::build(&[1005, 6, 99, 104, 1, 99, 1]);
+    assert_eq!(vec![0, 3], cfg.blocks.keys().copied().collect::<Vec<_>>());
+    assert_eq!(Exit::InvalidBranch { not_taken: 3 }, cfg.blocks[&0].exit);
+    assert!(cfg.to_dot().contains("(branch out of image)\\l"));
+
+    // Nothing gets a block past the end of the image
+    let cfg = Cfg::build(&[1005, 2, 0]);
+    assert_eq!(vec![0], cfg.blocks.keys().copied().collect::<Vec<_>>());
+    assert_eq!(Exit::Branch { taken: 0, not_taken: 3 }, cfg.blocks[&0].exit);
+    assert!(!cfg.to_dot().contains("b3"));
+  }
+
+  #[test]
+  fn ignores_constants_that_overflow() {
+    let cfg = Cfg::build(&[1102, Value::MAX, 2, 0, 1101, Value::MAX, 1, 0, 99]);
+    assert_eq!(vec![0], cfg.blocks.keys().copied().collect::<Vec<_>>());
+    assert_eq!(Exit::Halt, cfg.blocks[&0].exit);
+    assert_eq!(3, cfg.blocks[&0].lines.len());
+  }
+}
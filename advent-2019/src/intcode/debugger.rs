@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use advent_shared::AdventParse;
+use anyhow::*;
+
+use super::disassembler::disassemble_from;
+use super::{Computer, Opcode, Parameter, Position, RunState, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AdventParse)]
+pub enum Access {
+  #[pattern("read")]
+  Read,
+  #[pattern("write")]
+  Write,
+  #[pattern("rw")]
+  ReadWrite,
+}
+
+impl Access {
+  fn matches(self, write: bool) -> bool {
+    match self {
+      Access::Read => !write,
+      Access::Write => write,
+      Access::ReadWrite => true,
+    }
+  }
+}
+
+/// A line of input to the debugger REPL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AdventParse)]
+pub enum Command {
+  #[pattern("step")]
+  Step,
+  #[pattern("step {}")]
+  StepN(usize),
+  #[pattern("continue")]
+  Continue,
+  #[pattern("output")]
+  RunToOutput,
+  #[pattern("break {}")]
+  Break(Position),
+  #[pattern("delete {}")]
+  Delete(Position),
+  #[pattern("watch {} {}")]
+  Watch(Position, Access),
+  #[pattern("unwatch {}")]
+  Unwatch(Position),
+  #[pattern("mem {} {}")]
+  Memory(Position, usize),
+  #[pattern("input {}")]
+  Input(Value),
+  #[pattern("list")]
+  List,
+  #[pattern("info")]
+  Info,
+  #[pattern("quit")]
+  Quit,
+}
+
+/// The most words `mem` will show at once.
+const MAX_DUMP: usize = 1024;
+
+/// Why the debugger handed control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+  Breakpoint,
+  /// The instruction at `ip` just read or wrote a watched address.
+  Watchpoint { ip: Position, address: Position, write: bool },
+  State(RunState),
+}
+
+pub struct Debugger {
+  pub computer: Computer,
+  pub breakpoints: BTreeSet<Position>,
+  pub watchpoints: BTreeMap<Position, Access>,
+}
+
+impl Debugger {
+  pub fn new(computer: Computer) -> Self {
+    Debugger { computer, breakpoints: BTreeSet::new(), watchpoints: BTreeMap::new() }
+  }
+
+  /// Executes a single instruction, ignoring breakpoints.
+  pub fn step(&mut self) -> Result<Stop> {
+    let ip = self.computer.instruction_pointer;
+    let accesses = self.accesses(&self.computer.next_opcode()?)?;
+    let state = self.computer.step()?;
+    if state != RunState::NeedsInput {
+      let hit = accesses.into_iter().find(|(address, write)| {
+        self.watchpoints.get(address).is_some_and(|access| access.matches(*write))
+      });
+      if let Some((address, write)) = hit {
+        return Ok(Stop::Watchpoint { ip, address, write });
+      }
+    }
+    Ok(Stop::State(state))
+  }
+
+  /// Runs until a breakpoint or watchpoint is hit, the program blocks on input
+  /// or halts, or, with `stop_on_output`, it outputs something. A breakpoint
+  /// on the current instruction doesn't count, so it's always possible to
+  /// continue past one.
+  pub fn resume(&mut self, stop_on_output: bool) -> Result<Stop> {
+    loop {
+      match self.step()? {
+        Stop::State(RunState::Running) => {},
+        Stop::State(RunState::Output(_)) if !stop_on_output => {},
+        stop => return Ok(stop),
+      }
+      if self.breakpoints.contains(&self.computer.instruction_pointer) {
+        return Ok(Stop::Breakpoint);
+      }
+    }
+  }
+
+  /// Runs `command`, returning what to show the user.
+  pub fn execute(&mut self, command: Command) -> Result<String> {
+    let message = match command {
+      Command::Step => {
+        let stop = self.step()?;
+        self.describe(stop)
+      },
+      Command::StepN(n) => {
+        let mut stop = Stop::State(RunState::Running);
+        for _ in 0..n {
+          stop = self.step()?;
+          if let Stop::State(RunState::Running) | Stop::State(RunState::Output(_)) = stop {
+            continue;
+          }
+          break;
+        }
+        self.describe(stop)
+      },
+      Command::Continue | Command::RunToOutput => {
+        let stop = self.resume(command == Command::RunToOutput)?;
+        self.describe(stop)
+      },
+      Command::Break(address) => {
+        self.breakpoints.insert(address);
+        format!("Breakpoint set at {}", address)
+      },
+      Command::Delete(address) => if self.breakpoints.remove(&address) {
+        format!("Deleted breakpoint at {}", address)
+      } else {
+        format!("No breakpoint at {}", address)
+      },
+      Command::Watch(address, access) => {
+        self.watchpoints.insert(address, access);
+        format!("Watching [{}] for {:?}", address, access)
+      },
+      Command::Unwatch(address) => match self.watchpoints.remove(&address) {
+        Some(_) => format!("Stopped watching [{}]", address),
+        None => format!("No watchpoint on [{}]", address),
+      },
+      Command::Memory(start, len) => {
+        if len > MAX_DUMP {
+          bail!("Can't show more than {} words at once", MAX_DUMP);
+        }
+        let end = start.checked_add(len).ok_or_else(|| anyhow!("[{}] is past the end of memory", start))?;
+        (start..end)
+          .step_by(8)
+          .map(|row| {
+            let values = (row..row.saturating_add(8).min(end)).map(|a| self.computer.memory[a].to_string());
+            format!("{:>5}: {}", row, values.collect::<Vec<_>>().join(" "))
+          })
+          .collect::<Vec<_>>()
+          .join("\n")
+      },
+      Command::Input(value) => {
+        self.computer.input.push_back(value);
+        format!("Queued input {}", value)
+      },
+      Command::List => self.listing(5),
+      Command::Info => format!(
+        "ip: {}, relative base: {}\ninput: {:?}\noutput: {:?}\nbreakpoints: {:?}\nwatchpoints: {:?}",
+        self.computer.instruction_pointer,
+        self.computer.relative_base,
+        self.computer.input,
+        self.computer.output,
+        self.breakpoints,
+        self.watchpoints,
+      ),
+      Command::Quit => String::new(),
+    };
+    Ok(message)
+  }
+
+  /// Every address `opcode` would read or write, paired with whether it's a write.
+  fn accesses(&self, opcode: &Opcode) -> Result<Vec<(Position, bool)>> {
+    let (reads, write) = opcode.operands();
+    let reads = reads.into_iter()
+      .filter(|p| !matches!(p, Parameter::Immediate(_)))
+      .map(|p| (p, false));
+    reads.chain(write.map(|p| (p, true)))
+      .map(|(p, write)| Ok((self.computer.address(&p)?, write)))
+      .collect()
+  }
+
+  fn describe(&self, stop: Stop) -> String {
+    let reason = match stop {
+      Stop::Breakpoint => format!("Breakpoint at {}", self.computer.instruction_pointer),
+      Stop::Watchpoint { ip, address, write } => {
+        let verb = if write { "wrote" } else { "read" };
+        format!("{} {} [{}] = {}", ip, verb, address, self.computer.memory[address])
+      },
+      Stop::State(RunState::Running) => String::new(),
+      Stop::State(RunState::Output(value)) => format!("Output: {}", value),
+      Stop::State(RunState::NeedsInput) => "Waiting for input".to_string(),
+      Stop::State(RunState::Halted) => "Halted".to_string(),
+    };
+    let next = self.listing(1);
+    if reason.is_empty() { next } else { format!("{}\n{}", reason, next) }
+  }
+
+  /// The next `count` instructions, marking the current one and any breakpoints.
+  fn listing(&self, count: usize) -> String {
+    let ip = self.computer.instruction_pointer;
+    disassemble_from(self.computer.memory.image(), ip, count)
+      .iter()
+      .map(|line| {
+        let marker = if line.address == ip { '>' } else if self.breakpoints.contains(&line.address) { '*' } else { ' ' };
+        format!("{}{}", marker, line)
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::assembler::assemble;
+
+  fn counter() -> Debugger {
+    Debugger::new(assemble("
+      loop: ADD [n], #1 -> [n]
+            OUTPUT [n]
+            LESSTHAN [n], #3 -> [flag]
+            JUMPIFTRUE [flag], #loop
+            HALT
+      n:    DATA 0
+      flag: DATA 0
+    ").unwrap().into())
+  }
+
+  #[test]
+  fn parses_commands() {
+    assert_eq!(Ok(Command::Step), "step".parse());
+    assert_eq!(Ok(Command::StepN(10)), "step 10".parse());
+    assert_eq!(Ok(Command::Watch(12, Access::ReadWrite)), "watch 12 rw".parse());
+    assert_eq!(Ok(Command::Memory(0, 16)), "mem 0 16".parse());
+    assert_eq!(Ok(Command::Input(-1)), "input -1".parse());
+    assert!("break here".parse::<Command>().is_err());
+  }
+
+  #[test]
+  fn breakpoints() {
+    let mut debugger = counter();
+    debugger.breakpoints.insert(4);
+    assert_eq!(Stop::Breakpoint, debugger.resume(false).unwrap());
+    assert_eq!(4, debugger.computer.instruction_pointer);
+    assert_eq!(Stop::Breakpoint, debugger.resume(false).unwrap());
+    assert_eq!(1, debugger.computer.output.len());
+    debugger.breakpoints.clear();
+    assert_eq!(Stop::State(RunState::Halted), debugger.resume(false).unwrap());
+    assert_eq!(vec![1, 2, 3], debugger.computer.output.iter().copied().collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn watchpoints() {
+    let mut debugger = counter();
+    debugger.watchpoints.insert(15, Access::Write);
+    assert_eq!(Stop::Watchpoint { ip: 6, address: 15, write: true }, debugger.resume(false).unwrap());
+    debugger.watchpoints.insert(14, Access::Read);
+    assert_eq!(Stop::Watchpoint { ip: 0, address: 14, write: false }, debugger.resume(false).unwrap());
+    assert_eq!(Ok("Stopped watching [14]".to_string()), debugger.execute(Command::Unwatch(14)).map_err(|e| e.to_string()));
+  }
+
+  #[test]
+  fn run_to_output() {
+    let mut debugger = counter();
+    assert_eq!(Stop::State(RunState::Output(1)), debugger.resume(true).unwrap());
+    assert_eq!(Stop::State(RunState::Output(2)), debugger.resume(true).unwrap());
+  }
+
+  #[test]
+  fn blocks_on_input() {
+    let mut debugger = Debugger::new(vec![3, 5, 4, 5, 99].into());
+    debugger.watchpoints.insert(5, Access::ReadWrite);
+    assert_eq!(Stop::State(RunState::NeedsInput), debugger.step().unwrap());
+    debugger.execute(Command::Input(42)).unwrap();
+    assert_eq!(Stop::Watchpoint { ip: 0, address: 5, write: true }, debugger.step().unwrap());
+    assert_eq!("2 read [5] = 42\n>    4: 99                       HALT", debugger.execute(Command::Step).unwrap());
+  }
+
+  #[test]
+  fn inspects_memory() {
+    let mut debugger = counter();
+    assert_eq!("   13: 99 0 0", debugger.execute(Command::Memory(13, 3)).unwrap());
+    assert_eq!("    0: 1001 14 1 14 4 14 1007 14\n    8: 3", debugger.execute(Command::Memory(0, 9)).unwrap());
+    assert!(debugger.execute(Command::Memory(usize::MAX, 1)).is_err());
+    assert_eq!(format!("{}: 0 0", usize::MAX - 3), debugger.execute(Command::Memory(usize::MAX - 3, 2)).unwrap());
+    assert!(debugger.execute(Command::Memory(0, MAX_DUMP + 1)).is_err());
+    debugger.breakpoints.insert(4);
+    let listing = debugger.execute(Command::List).unwrap();
+    assert!(listing.starts_with(">    0: 1001,14,1,14"));
+    assert!(listing.lines().nth(1).unwrap().starts_with("*    4: 4,14"));
+  }
+}
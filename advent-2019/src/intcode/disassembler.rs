@@ -0,0 +1,143 @@
+use std::fmt;
+use std::ops::Range;
+
+use super::{Computer, Opcode, Parameter, Position, Value};
+
+impl<W: fmt::Display> fmt::Display for Parameter<W> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Parameter::Position(addr) => write!(f, "[{}]", addr),
+      Parameter::Immediate(val) => write!(f, "#{}", val),
+      Parameter::Relative(offset) => {
+        // Negative offsets bring their own sign
+        let offset = offset.to_string();
+        let sign = if offset.starts_with('-') { "" } else { "+" };
+        write!(f, "[rb{}{}]", sign, offset)
+      },
+    }
+  }
+}
+
+impl<W> Opcode<W> {
+  pub fn mnemonic(&self) -> &'static str {
+    use Opcode::*;
+    match self {
+      Add { .. } => "ADD",
+      Mult { .. } => "MULT",
+      Input { .. } => "INPUT",
+      Output { .. } => "OUTPUT",
+      JumpIfTrue { .. } => "JUMPIFTRUE",
+      JumpIfFalse { .. } => "JUMPIFFALSE",
+      LessThan { .. } => "LESSTHAN",
+      Equals { .. } => "EQUALS",
+      AdjustRelativeBase { .. } => "ADJUSTRELATIVEBASE",
+      Halt => "HALT",
+    }
+  }
+}
+
+impl<W: fmt::Display> fmt::Display for Opcode<W> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    use Opcode::*;
+    let mnemonic = self.mnemonic();
+    match self {
+      Add { left, right, store } | Mult { left, right, store }
+      | LessThan { left, right, store } | Equals { left, right, store } => {
+        write!(f, "{} {}, {} -> {}", mnemonic, left, right, store)
+      },
+      Input { destination } => write!(f, "{} -> {}", mnemonic, destination),
+      Output { source } => write!(f, "{} {}", mnemonic, source),
+      JumpIfTrue { source, dest } | JumpIfFalse { source, dest } => {
+        write!(f, "{} {}, {}", mnemonic, source, dest)
+      },
+      AdjustRelativeBase { offset } => write!(f, "{} {}", mnemonic, offset),
+      Halt => write!(f, "{}", mnemonic),
+    }
+  }
+}
+
+/// One disassembled instruction, or a single word of data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+  pub address: Position,
+  pub words: Vec<Value>,
+  pub opcode: Option<Opcode>,
+}
+
+impl fmt::Display for Line {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let words = self.words.iter().map(|w| w.to_string()).collect::<Vec<_>>().join(",");
+    match &self.opcode {
+      Some(opcode) => write!(f, "{:>5}: {:<24} {}", self.address, words, opcode),
+      None => write!(f, "{:>5}: {:<24} DATA {}", self.address, words, self.words[0]),
+    }
+  }
+}
+
+/// Disassembles a whole memory image with a linear sweep, so anything that
+/// doesn't decode, or would run off the end of the image, comes out as data.
+pub fn disassemble(image: &[Value]) -> Vec<Line> {
+  disassemble_with(image, &[])
+}
+
+/// Like `disassemble`, but treats every address in `data` as data even if it
+/// happens to decode as an instruction.
+pub fn disassemble_with(image: &[Value], data: &[Range<Position>]) -> Vec<Line> {
+  sweep(image, data, 0, usize::MAX)
+}
+
+/// At most `count` lines, starting from `start` rather than the top of the image.
+pub fn disassemble_from(image: &[Value], start: Position, count: usize) -> Vec<Line> {
+  sweep(image, &[], start, count)
+}
+
+fn sweep(image: &[Value], data: &[Range<Position>], start: Position, count: usize) -> Vec<Line> {
+  let mut computer: Computer = image.to_vec().into();
+  let is_data = |addr: Position| data.iter().any(|range| range.contains(&addr));
+  let mut lines = vec![];
+  let mut address = start;
+  while address < image.len() && lines.len() < count {
+    computer.instruction_pointer = address;
+    let line = match computer.next_opcode() {
+      Ok(opcode) if address + opcode.size() <= image.len()
+        && !(address..address + opcode.size()).any(is_data) => {
+        let words = image[address..address + opcode.size()].to_vec();
+        Line { address, words, opcode: Some(opcode) }
+      },
+      _ => Line { address, words: vec![image[address]], opcode: None },
+    };
+    address += line.words.len();
+    lines.push(line);
+  }
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn text(image: &[Value]) -> Vec<String> {
+    disassemble(image).iter().map(|line| line.to_string()[32..].to_string()).collect()
+  }
+
+  #[test]
+  fn operands() {
+    assert_eq!(vec!["ADD [12], #5 -> [40]"], text(&[1001, 12, 5, 40]));
+    assert_eq!(vec!["MULT [rb+3], [rb-2] -> [rb+0]"], text(&[22202, 3, -2, 0]));
+    assert_eq!(vec!["INPUT -> [rb+1]", "OUTPUT #7", "JUMPIFFALSE [3], #0", "HALT"],
+      text(&[203, 1, 104, 7, 1006, 3, 0, 99]));
+  }
+
+  #[test]
+  fn data_and_unknown_opcodes() {
+    assert_eq!(vec!["HALT", "DATA 42", "DATA 4"], text(&[99, 42, 4]));
+    assert_eq!(vec!["DATA 301", "HALT"], text(&[301, 99]));
+
+    let lines = disassemble_with(&[1, 0, 0, 0, 99, 1, 2, 3], &[5..6, 7..8]);
+    assert_eq!(vec![0, 4, 5, 6, 7], lines.iter().map(|l| l.address).collect::<Vec<_>>());
+    assert_eq!("    0: 1,0,0,0                  ADD [0], [0] -> [0]", lines[0].to_string());
+
+    let lines = disassemble_from(&[1, 0, 0, 0, 99, 1, 2, 3], 4, 2);
+    assert_eq!(vec![4, 5], lines.iter().map(|l| l.address).collect::<Vec<_>>());
+  }
+}
@@ -0,0 +1,47 @@
+use std::fmt;
+
+use super::{Position, Value, Word};
+
+/// Everything that can go wrong running an Intcode program. Each one carries
+/// the address of the instruction that failed and its raw value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntcodeError<W = Value> {
+  UnknownOpcode { ip: Position, instruction: W },
+  UnknownMode { ip: Position, instruction: W, mode: i64 },
+  /// An operand or jump target pointed below address 0.
+  NegativeAddress { ip: Position, instruction: W, address: W },
+  AddressTooLarge { ip: Position, instruction: W, address: W },
+  /// Only in strict mode; otherwise an immediate store writes to that address.
+  ImmediateWrite { ip: Position, instruction: W },
+  /// Only in strict mode: a read of an address that was never loaded or written.
+  UninitializedRead { ip: Position, instruction: W, address: Position },
+  /// An arithmetic result that doesn't fit in the word size.
+  Overflow { ip: Position, instruction: W },
+  StepLimit { ip: Position, instruction: W, limit: usize },
+}
+
+impl<W: Word> fmt::Display for IntcodeError<W> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    use IntcodeError::*;
+    match self {
+      UnknownOpcode { ip, instruction } =>
+        write!(f, "Unknown opcode {} at {}", instruction, ip),
+      UnknownMode { ip, instruction, mode } =>
+        write!(f, "Unknown parameter mode {} in instruction {} at {}", mode, instruction, ip),
+      NegativeAddress { ip, instruction, address } =>
+        write!(f, "Negative address {} used by instruction {} at {}", address, instruction, ip),
+      AddressTooLarge { ip, instruction, address } =>
+        write!(f, "Address {} used by instruction {} at {} is too large", address, instruction, ip),
+      ImmediateWrite { ip, instruction } =>
+        write!(f, "Immediate mode write by instruction {} at {}", instruction, ip),
+      UninitializedRead { ip, instruction, address } =>
+        write!(f, "Read of uninitialized address {} by instruction {} at {}", address, instruction, ip),
+      Overflow { ip, instruction } =>
+        write!(f, "Overflow in instruction {} at {}", instruction, ip),
+      StepLimit { ip, instruction, limit } =>
+        write!(f, "Step limit of {} reached at {} (instruction {})", limit, ip, instruction),
+    }
+  }
+}
+
+impl<W: Word> std::error::Error for IntcodeError<W> {}
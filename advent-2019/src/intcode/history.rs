@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+
+use super::{Computer, Position, Value, Word};
+
+/// Enough to put back everything one instruction changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Undo<W> {
+  pub ip: Position,
+  pub relative_base: W,
+  /// The cell written to and what it held before, or `None` if it had never
+  /// been set.
+  pub write: Option<(Position, Option<W>)>,
+  pub input: Option<W>,
+  pub output: Option<W>,
+}
+
+/// The most recent instructions a `Computer` has executed, newest last,
+/// keeping no more than `limit` of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct History<W = Value> {
+  undo: VecDeque<Undo<W>>,
+  limit: usize,
+}
+
+impl<W> History<W> {
+  pub fn new(limit: usize) -> Self {
+    History { undo: VecDeque::new(), limit }
+  }
+
+  pub fn len(&self) -> usize {
+    self.undo.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.undo.is_empty()
+  }
+
+  pub(super) fn record(&mut self, undo: Undo<W>) {
+    if self.limit == 0 {
+      return;
+    }
+    if self.undo.len() == self.limit {
+      self.undo.pop_front();
+    }
+    self.undo.push_back(undo);
+  }
+}
+
+impl<W: Word> Computer<W> {
+  /// Starts recording the last `limit` instructions executed, so they can be
+  /// stepped back through.
+  pub fn with_history(mut self, limit: usize) -> Self {
+    self.history = Some(History::new(limit));
+    self
+  }
+
+  /// Undoes the most recent instruction, returning false if there's nothing
+  /// left in the history to undo. Input it consumed goes back on the front of
+  /// `input`, and output it produced comes off the back of `output`, if it
+  /// hasn't been taken already.
+  pub fn step_back(&mut self) -> bool {
+    let undo = match self.history.as_mut().and_then(|history| history.undo.pop_back()) {
+      Some(undo) => undo,
+      None => return false,
+    };
+    self.instruction_pointer = undo.ip;
+    self.relative_base = undo.relative_base;
+    match undo.write {
+      Some((address, Some(old))) => self.memory[address] = old,
+      Some((address, None)) => self.memory.forget(address),
+      None => {},
+    }
+    if let Some(value) = undo.input {
+      self.input.push_front(value);
+    }
+    if undo.output.is_some() && self.output.back() == undo.output.as_ref() {
+      self.output.pop_back();
+    }
+    self.steps -= 1;
+    true
+  }
+
+  /// Steps back to the most recent time the instruction at `ip` was about to
+  /// run, at least one instruction ago. If that's further back than the
+  /// history goes, nothing is undone and this returns false.
+  pub fn run_back_to(&mut self, ip: Position) -> bool {
+    let history = match &self.history {
+      Some(history) => history,
+      None => return false,
+    };
+    match history.undo.iter().rev().position(|undo| undo.ip == ip) {
+      Some(n) => {
+        for _ in 0..=n {
+          self.step_back();
+        }
+        true
+      },
+      None => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::{assembler::assemble, RunState};
+
+  // Adds up its inputs, printing the running total, until it's given a 0
+  fn totaller() -> Computer {
+    assemble("
+      loop:  INPUT -> [n]
+             JUMPIFFALSE [n], #end
+             ADD [total], [n] -> [total]
+             OUTPUT [total]
+             JUMPIFTRUE #1, #loop
+      end:   ADD [total], #0 -> [1000]
+             HALT
+      n:     DATA 0
+      total: DATA 0
+    ").unwrap().into()
+  }
+
+  #[test]
+  fn steps_back_exactly() {
+    let mut computer = totaller().with_history(100);
+    computer.input.extend(vec![3, 4, 0]);
+    let mut states = vec![computer.to_snapshot()];
+    while computer.step().unwrap() != RunState::Halted {
+      states.push(computer.to_snapshot());
+    }
+    assert_eq!(vec![3, 7], computer.output.iter().copied().collect::<Vec<_>>());
+    assert_eq!(7, computer.memory[1000]);
+
+    // Halting, however many times, isn't recorded, so there's nothing to undo
+    // until the instruction before it
+    assert_eq!(RunState::Halted, computer.step().unwrap());
+    assert_eq!(states.len() - 1, computer.history.as_ref().unwrap().len());
+    while let Some(state) = states.pop() {
+      assert_eq!(state, computer.to_snapshot());
+      if !states.is_empty() {
+        assert!(computer.step_back());
+      }
+    }
+    assert!(!computer.step_back());
+    assert_eq!(0, computer.steps);
+    assert!(!computer.memory.is_initialized(1000));
+  }
+
+  #[test]
+  fn runs_back_to_an_instruction() {
+    let mut computer = totaller().with_history(100);
+    computer.input.extend(vec![3, 4]);
+    assert_eq!(RunState::NeedsInput, computer.run_until_blocked().unwrap());
+    assert_eq!(vec![3, 7], computer.output.iter().copied().collect::<Vec<_>>());
+
+    // Back to just before the second OUTPUT
+    assert!(computer.run_back_to(9));
+    assert_eq!(9, computer.instruction_pointer);
+    assert_eq!(vec![3], computer.output.iter().copied().collect::<Vec<_>>());
+    assert_eq!(7, computer.memory[20]);
+    assert!(!computer.run_back_to(99));
+    assert_eq!(9, computer.instruction_pointer);
+
+    // And back to the first INPUT, which puts both inputs back
+    assert!(computer.run_back_to(0));
+    assert!(computer.run_back_to(0));
+    assert_eq!(vec![3, 4], computer.input.iter().copied().collect::<Vec<_>>());
+    assert!(computer.output.is_empty());
+  }
+
+  #[test]
+  fn history_is_bounded() {
+    let mut computer = totaller().with_history(3);
+    computer.input.extend(vec![1, 2, 3]);
+    computer.run_until_blocked().unwrap();
+    assert_eq!(3, computer.history.as_ref().unwrap().len());
+    assert!(computer.step_back() && computer.step_back() && computer.step_back());
+    assert!(!computer.step_back());
+
+    let mut computer = totaller();
+    computer.input.push_back(1);
+    computer.run_until_blocked().unwrap();
+    assert!(!computer.step_back());
+  }
+}
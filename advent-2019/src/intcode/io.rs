@@ -0,0 +1,156 @@
+//! Where a `Computer` gets its input from and sends its output to.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender};
+
+use anyhow::Result;
+
+use advent_shared::parsers::ints;
+
+use super::Value;
+
+pub trait Input<W = Value> {
+  /// The next input value, or `None` if there isn't one yet, in which case the
+  /// computer stops with `RunState::NeedsInput` without consuming anything.
+  fn read(&mut self) -> Option<W>;
+}
+
+pub trait Output<W = Value> {
+  fn write(&mut self, value: W);
+}
+
+impl<W> Input<W> for VecDeque<W> {
+  fn read(&mut self) -> Option<W> {
+    self.pop_front()
+  }
+}
+
+impl<W> Output<W> for VecDeque<W> {
+  fn write(&mut self, value: W) {
+    self.push_back(value);
+  }
+}
+
+impl<W> Output<W> for Vec<W> {
+  fn write(&mut self, value: W) {
+    self.push(value);
+  }
+}
+
+/// Waits for each value from another thread, and reads nothing once every
+/// sender has gone away.
+impl<W> Input<W> for Receiver<W> {
+  fn read(&mut self) -> Option<W> {
+    self.recv().ok()
+  }
+}
+
+/// Sends each value to another thread. Anything sent after the receiver has
+/// gone away is dropped.
+impl<W> Output<W> for Sender<W> {
+  fn write(&mut self, value: W) {
+    let _ = self.send(value);
+  }
+}
+
+/// Input from a callback, e.g. `FromFn(|| Some(joystick_position()))`.
+pub struct FromFn<F>(pub F);
+
+impl<W, F: FnMut() -> Option<W>> Input<W> for FromFn<F> {
+  fn read(&mut self) -> Option<W> {
+    (self.0)()
+  }
+}
+
+/// Output to a callback, e.g. `Sink(|v| println!("{}", v))`.
+pub struct Sink<F>(pub F);
+
+impl<W, F: FnMut(W)> Output<W> for Sink<F> {
+  fn write(&mut self, value: W) {
+    (self.0)(value)
+  }
+}
+
+/// Input from any iterator of values.
+pub struct FromIter<T>(pub T);
+
+impl<T: Iterator> Input<T::Item> for FromIter<T> {
+  fn read(&mut self) -> Option<T::Item> {
+    self.0.next()
+  }
+}
+
+/// Every integer in `file`, in order, however they're separated.
+pub fn script(file: PathBuf) -> Result<FromIter<std::vec::IntoIter<Value>>> {
+  Ok(FromIter(ints::<Value>(file)?.into_iter()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::{assembler::assemble, Computer, RunState};
+  use std::{fs, sync::mpsc, thread};
+
+  // Adds pairs of inputs until it's given a 0
+  fn adder() -> Computer {
+    assemble("
+      loop: INPUT -> [a]
+            JUMPIFFALSE [a], #end
+            INPUT -> [b]
+            ADD [a], [b] -> [b]
+            OUTPUT [b]
+            JUMPIFTRUE #1, #loop
+      end:  HALT
+      a:    DATA 0
+      b:    DATA 0
+    ").unwrap().into()
+  }
+
+  #[test]
+  fn callbacks() {
+    let mut inputs = vec![1, 2, 30, 40, 0].into_iter();
+    let mut outputs = vec![];
+    let mut computer = adder().with_io(FromFn(|| inputs.next()), Sink(|v| outputs.push(v)));
+    assert_eq!(RunState::Halted, computer.run_until_blocked().unwrap());
+    assert_eq!(vec![3, 70], outputs);
+  }
+
+  #[test]
+  fn iterator_runs_dry() {
+    let mut computer = adder().with_io(FromIter(vec![1, 2, 3].into_iter()), vec![]);
+    assert_eq!(RunState::NeedsInput, computer.run_until_blocked().unwrap());
+    assert_eq!(vec![3], computer.output);
+  }
+
+  #[test]
+  fn channels_between_threads() {
+    let (to_first, first_input) = mpsc::channel();
+    let (to_second, second_input) = mpsc::channel();
+    let (to_main, results) = mpsc::channel();
+    let first = adder().with_io(first_input, to_second.clone());
+    let second = adder().with_io(second_input, to_main);
+    let spawn = |mut computer: Computer<_, _, _>| thread::spawn(move || computer.run_until_blocked().unwrap());
+    let (first, second) = (spawn(first), spawn(second));
+
+    // The first adder's sums are paired up by the second
+    for value in &[1, 2, 3, 4, 0] {
+      to_first.send(*value).unwrap();
+    }
+    assert_eq!(RunState::Halted, first.join().unwrap());
+    to_second.send(0).unwrap();
+    assert_eq!(RunState::Halted, second.join().unwrap());
+    assert_eq!(vec![10], results.iter().collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn scripted_file() {
+    let path = std::env::temp_dir().join(format!("advent-2019-script-{}.txt", std::process::id()));
+    fs::write(&path, "5, 6\n7 -1\n0\n").unwrap();
+    let input = script(path.clone()).unwrap();
+    fs::remove_file(path).unwrap();
+    let mut computer = adder().with_io(input, VecDeque::new());
+    assert_eq!(RunState::Halted, computer.run_until_blocked().unwrap());
+    assert_eq!(vec![11, 6], computer.output.into_iter().collect::<Vec<_>>());
+  }
+}
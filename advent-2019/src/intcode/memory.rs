@@ -0,0 +1,64 @@
+use std::{collections::HashMap, ops::{Index, IndexMut}};
+
+use super::{Position, Value, Word};
+
+/// Intcode memory: the loaded program image, plus a sparse map for anything a
+/// program touches past the end of it. Every address reads as 0 until written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Memory<W = Value> {
+  image: Vec<W>,
+  extra: HashMap<Position, W>,
+  /// What unset addresses read as.
+  zero: W,
+}
+
+impl<W: Word> Memory<W> {
+  /// The program as it was loaded, with any writes made inside it since.
+  pub fn image(&self) -> &[W] {
+    &self.image
+  }
+
+  /// Everything written past the end of the image, in address order.
+  pub fn extra(&self) -> Vec<(Position, W)> {
+    let mut extra: Vec<_> = self.extra.iter().map(|(&addr, val)| (addr, val.clone())).collect();
+    extra.sort_unstable();
+    extra
+  }
+
+  /// Whether `addr` was part of the program or has been written since.
+  pub fn is_initialized(&self, addr: Position) -> bool {
+    addr < self.image.len() || self.extra.contains_key(&addr)
+  }
+
+  /// Makes an address past the end of the image uninitialized again.
+  pub(super) fn forget(&mut self, addr: Position) {
+    self.extra.remove(&addr);
+  }
+}
+
+impl<W> Index<Position> for Memory<W> {
+  type Output = W;
+
+  fn index(&self, addr: Position) -> &W {
+    match self.image.get(addr) {
+      Some(val) => val,
+      None => self.extra.get(&addr).unwrap_or(&self.zero),
+    }
+  }
+}
+
+impl<W: Clone> IndexMut<Position> for Memory<W> {
+  fn index_mut(&mut self, addr: Position) -> &mut W {
+    if addr < self.image.len() {
+      return &mut self.image[addr];
+    }
+    let zero = &self.zero;
+    self.extra.entry(addr).or_insert_with(|| zero.clone())
+  }
+}
+
+impl<W: Word> From<Vec<W>> for Memory<W> {
+  fn from(image: Vec<W>) -> Self {
+    Memory { image, extra: HashMap::new(), zero: W::default() }
+  }
+}
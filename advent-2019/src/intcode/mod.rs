@@ -1,367 +1,734 @@
 use std::collections::VecDeque;
-
-use anyhow::*;
+use std::convert::TryFrom;
+
+mod error;
+mod memory;
+mod word;
+pub mod arcade;
+pub mod ascii;
+pub mod assembler;
+pub mod cached;
+pub mod cfg;
+pub mod debugger;
+pub mod disassembler;
+pub mod history;
+pub mod io;
+pub mod network;
+pub mod pipeline;
+pub mod robot;
+pub mod snapshot;
+pub mod symbolic;
+pub mod trace;
+pub use error::IntcodeError;
+pub use history::History;
+use history::Undo;
+pub use memory::Memory;
+pub use io::{Input, Output};
+pub use trace::{NoTrace, Tracer};
+pub use word::Word;
 
 pub type Position = usize;
+/// The word type everything uses unless it asks for another.
 pub type Value = isize;
 
-#[derive(Debug)]
-pub enum Parameter {
-  Position(Value),
-  Immediate(Value)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parameter<W = Value> {
+  Position(W),
+  Immediate(W),
+  Relative(W),
 }
 
-impl Parameter {
-  pub fn parse<'a>(instruction: &'a mut std::slice::Iter<'a, Value>) -> impl Iterator<Item = Parameter> + 'a {
-    struct Iter<'a> { opcode: isize, params: &'a mut std::slice::Iter<'a, Value> }
-    impl<'a> Iterator for Iter<'a> {
-      type Item = Parameter;
-      fn next(&mut self) -> Option<Self::Item> {
-        let value =  self.params.next().unwrap();
-        let mode = match self.opcode % 10 {
-          0 => Parameter::Position(*value),
-          1 => Parameter::Immediate(*value),
-          _ => panic!("Unimplemented parameter mode")
-        };
-        self.opcode /= 10;
-        return Some(mode);
-      }
+impl<W> Parameter<W> {
+  /// Decodes the `n`th parameter (counting from 1) of the instruction `code`,
+  /// whose raw value in memory is `value`. Fails with the mode if it's not
+  /// one we know.
+  pub fn decode(code: i64, n: u32, value: W) -> Result<Parameter<W>, i64> {
+    match (code / 10i64.pow(n + 1)) % 10 {
+      0 => Ok(Parameter::Position(value)),
+      1 => Ok(Parameter::Immediate(value)),
+      2 => Ok(Parameter::Relative(value)),
+      mode => Err(mode),
     }
-    let opcode = instruction.next().unwrap() / 100;
-    return Iter { opcode, params: instruction };
   }
 }
 
-#[derive(Debug)]
-pub enum Opcode {
-  Add { left: Parameter, right: Parameter, store: Parameter },
-  Mult { left: Parameter, right: Parameter, store: Parameter },
-  Input { destination: Parameter },
-  Output { source: Parameter },
-  JumpIfTrue { source: Parameter, dest: Parameter },
-  JumpIfFalse { source: Parameter, dest: Parameter },
-  LessThan { left: Parameter, right: Parameter, store: Parameter },
-  Equals { left: Parameter, right: Parameter, store: Parameter },
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode<W = Value> {
+  Add { left: Parameter<W>, right: Parameter<W>, store: Parameter<W> },
+  Mult { left: Parameter<W>, right: Parameter<W>, store: Parameter<W> },
+  Input { destination: Parameter<W> },
+  Output { source: Parameter<W> },
+  JumpIfTrue { source: Parameter<W>, dest: Parameter<W> },
+  JumpIfFalse { source: Parameter<W>, dest: Parameter<W> },
+  LessThan { left: Parameter<W>, right: Parameter<W>, store: Parameter<W> },
+  Equals { left: Parameter<W>, right: Parameter<W>, store: Parameter<W> },
+  AdjustRelativeBase { offset: Parameter<W> },
   Halt,
 }
 
-impl Opcode {
+impl<W: Clone> Opcode<W> {
   pub fn size(&self) -> usize {
     use Opcode::*;
     match self {
       Mult { .. } | Add { .. } | LessThan { .. } | Equals { .. } => 4,
       JumpIfTrue { .. } | JumpIfFalse { .. } => 3,
-      Input { .. } | Output { .. } => 2,
+      Input { .. } | Output { .. } | AdjustRelativeBase { .. } => 2,
       Halt => 1,
     }
   }
+  /// The parameters this instruction reads, and the one it writes to, if any.
+  pub fn operands(&self) -> (Vec<Parameter<W>>, Option<Parameter<W>>) {
+    use Opcode::*;
+    match self {
+      Add { left, right, store } | Mult { left, right, store }
+      | LessThan { left, right, store } | Equals { left, right, store } => {
+        (vec![left.clone(), right.clone()], Some(store.clone()))
+      },
+      Input { destination } => (vec![], Some(destination.clone())),
+      Output { source } | AdjustRelativeBase { offset: source } => (vec![source.clone()], None),
+      JumpIfTrue { source, dest } | JumpIfFalse { source, dest } => (vec![source.clone(), dest.clone()], None),
+      Halt => (vec![], None),
+    }
+  }
 }
 
 macro_rules! binary_assign {
-  ($opcode:ident, $memory:expr, $idx:expr, $parameters:expr) => {
-    {
-      let left = $parameters.next().unwrap();
-      let right = $parameters.next().unwrap();
-      let store = Parameter::Immediate($memory[$idx + 3]);
-      Ok(Opcode::$opcode { left, right, store })
+  ($opcode:ident, $parameter:expr) => {
+    Ok(Opcode::$opcode { left: $parameter(1)?, right: $parameter(2)?, store: $parameter(3)? })
+  }
+}
+
+impl<P> Opcode<P> {
+  /// Decodes `instruction`, found at `ip`, taking the raw value of its `n`th
+  /// parameter (counting from 1) from `parameter(n)`. The parameters can be
+  /// anything, not just words, so long as the instruction itself is known.
+  pub fn decode<W: Word>(ip: Position, instruction: &W, parameter: impl Fn(Position) -> P) -> Result<Opcode<P>, IntcodeError<W>> {
+    let unknown = || IntcodeError::UnknownOpcode { ip, instruction: instruction.clone() };
+    let code = instruction.to_i64().ok_or_else(unknown)?;
+    let parameter = |n: u32| {
+      Parameter::decode(code, n, parameter(n as Position))
+        .map_err(|mode| IntcodeError::UnknownMode { ip, instruction: instruction.clone(), mode })
+    };
+
+    match code % 100 {
+      1 => binary_assign!(Add, parameter),
+      2 => binary_assign!(Mult, parameter),
+      3 => Ok(Opcode::Input { destination: parameter(1)? }),
+      4 => Ok(Opcode::Output { source: parameter(1)? }),
+      5 => Ok(Opcode::JumpIfTrue { source: parameter(1)?, dest: parameter(2)? }),
+      6 => Ok(Opcode::JumpIfFalse { source: parameter(1)?, dest: parameter(2)? }),
+      7 => binary_assign!(LessThan, parameter),
+      8 => binary_assign!(Equals, parameter),
+      9 => Ok(Opcode::AdjustRelativeBase { offset: parameter(1)? }),
+      99 => Ok(Opcode::Halt),
+      _ => Err(unknown()),
     }
   }
 }
 
-#[derive(Clone)]
-pub struct Computer {
+/// Why the computer stopped, or `Running` if it can simply be stepped again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState<W = Value> {
+  Running,
+  /// Just produced this value; it's also been written to `output`.
+  Output(W),
+  /// Blocked on an `Input` that `input` had nothing for. Nothing was consumed,
+  /// so give it some input and resume.
+  NeedsInput,
+  Halted,
+}
+
+/// An Intcode machine. Input and output default to queues that the caller
+/// fills and drains between runs, but can be any `Input` and `Output`. Words
+/// are `Value`s unless the machine is loaded with another `Word` type.
+#[derive(Clone, Debug)]
+pub struct Computer<W = Value, I = VecDeque<W>, O = VecDeque<W>> {
   pub instruction_pointer: Position,
-  pub memory: Vec<Value>,
-  pub input: VecDeque<Value>,
-  pub output: VecDeque<Value>,
+  pub relative_base: W,
+  pub memory: Memory<W>,
+  pub input: I,
+  pub output: O,
+  /// Rejects immediate mode writes and reads of uninitialized memory, which
+  /// are otherwise allowed.
+  pub strict: bool,
+  /// Fails with `IntcodeError::StepLimit` once this many instructions have run.
+  pub step_limit: Option<usize>,
+  /// Instructions executed so far.
+  pub steps: usize,
+  /// What's needed to step back through recent instructions, if it's being kept.
+  pub history: Option<History<W>>,
 }
 
-impl Computer {
-  pub fn next_opcode(&self) -> Result<Opcode> {
+impl<W: Word, I, O> Computer<W, I, O> {
+  /// The same machine, reading from and writing to different devices.
+  pub fn with_io<I2, O2>(self, input: I2, output: O2) -> Computer<W, I2, O2> {
+    Computer {
+      instruction_pointer: self.instruction_pointer,
+      relative_base: self.relative_base,
+      memory: self.memory,
+      input,
+      output,
+      strict: self.strict,
+      step_limit: self.step_limit,
+      steps: self.steps,
+      history: self.history,
+    }
+  }
+  pub fn strict(mut self) -> Self {
+    self.strict = true;
+    self
+  }
+  pub fn with_step_limit(mut self, limit: usize) -> Self {
+    self.step_limit = Some(limit);
+    self
+  }
+  pub fn next_opcode(&self) -> Result<Opcode<W>, IntcodeError<W>> {
     let ip = self.instruction_pointer;
-    let opcode = self.memory[ip] % 100;
-    let mut parameters = self.memory[ip..].iter();
-    let mut parameters = Parameter::parse(&mut parameters);
-
-    match opcode {
-      1 => binary_assign!(Add, self.memory, ip, parameters),
-      2 => binary_assign!(Mult, self.memory, ip, parameters),
-      3 => Ok(Opcode::Input { destination: Parameter::Immediate(self.memory[ip + 1]) }),
-      4 => Ok(Opcode::Output { source: parameters.next().unwrap() }),
-      5 => Ok(Opcode::JumpIfTrue { source: parameters.next().unwrap(), dest: parameters.next().unwrap() }),
-      6 => Ok(Opcode::JumpIfFalse { source: parameters.next().unwrap(), dest: parameters.next().unwrap() }),
-      7 => binary_assign!(LessThan, self.memory, ip, parameters),
-      8 => binary_assign!(Equals, self.memory, ip, parameters),
-      99 => Ok(Opcode::Halt),
-      _ => bail!("Unknown Opcode {}", self.memory[ip])
+    Opcode::decode(ip, &self.memory[ip], |n| self.memory[ip + n].clone())
+  }
+  pub fn lookup(&self, param: &Parameter<W>) -> Result<W, IntcodeError<W>> {
+    match param {
+      Parameter::Immediate(val) => Ok(val.clone()),
+      Parameter::Position(_) | Parameter::Relative(_) => {
+        let address = self.address(param)?;
+        if self.strict && !self.memory.is_initialized(address) {
+          let (ip, instruction) = self.current();
+          return Err(IntcodeError::UninitializedRead { ip, instruction, address });
+        }
+        Ok(self.memory[address].clone())
+      },
     }
   }
-  pub fn lookup(&self, param: &Parameter) -> Value {
+  /// The address a parameter refers to, for parameters that are written through.
+  pub fn address(&self, param: &Parameter<W>) -> Result<Position, IntcodeError<W>> {
     match param {
-      Parameter::Immediate(val) => *val,
-      Parameter::Position(val) => self.memory[*val as Position],
+      Parameter::Immediate(_) if self.strict => {
+        let (ip, instruction) = self.current();
+        Err(IntcodeError::ImmediateWrite { ip, instruction })
+      },
+      Parameter::Position(addr) | Parameter::Immediate(addr) => self.position(addr),
+      Parameter::Relative(offset) => self.position(&self.add(&self.relative_base, offset)?),
     }
   }
-  pub fn write(&mut self, addr: Position, val: Value) {
-    if addr >= self.memory.len() {
-      self.memory.resize(addr + 1, 0);
+  fn position(&self, address: &W) -> Result<Position, IntcodeError<W>> {
+    if let Some(position) = address.to_i64().and_then(|addr| Position::try_from(addr).ok()) {
+      return Ok(position);
+    }
+    let (ip, instruction) = self.current();
+    let address = address.clone();
+    if address < W::default() {
+      Err(IntcodeError::NegativeAddress { ip, instruction, address })
+    } else {
+      Err(IntcodeError::AddressTooLarge { ip, instruction, address })
     }
+  }
+  fn add(&self, left: &W, right: &W) -> Result<W, IntcodeError<W>> {
+    left.checked_add(right).ok_or_else(|| self.overflow())
+  }
+  fn overflow(&self) -> IntcodeError<W> {
+    let (ip, instruction) = self.current();
+    IntcodeError::Overflow { ip, instruction }
+  }
+  /// The instruction pointer and the raw instruction there, for errors.
+  fn current(&self) -> (Position, W) {
+    (self.instruction_pointer, self.memory[self.instruction_pointer].clone())
+  }
+  pub fn write(&mut self, addr: Position, val: W) {
     self.memory[addr] = val;
   }
-  pub fn step(&mut self) -> Result<bool> {
+}
+
+impl<W: Word, I: Input<W>, O: Output<W>> Computer<W, I, O> {
+  pub fn step(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    self.step_with(&mut NoTrace)
+  }
+  /// Like `step`, but reporting what happens to `tracer`.
+  pub fn step_with<T: Tracer>(&mut self, tracer: &mut T) -> Result<RunState<W>, IntcodeError<W>> {
+    let opcode = self.next_opcode()?;
+    self.execute_with(opcode, tracer)
+  }
+  /// Executes `opcode` as though it had just been decoded at the instruction
+  /// pointer, for callers that keep their own decoded copy.
+  pub fn execute_with<T: Tracer>(&mut self, opcode: Opcode<W>, tracer: &mut T) -> Result<RunState<W>, IntcodeError<W>> {
     use Opcode::*;
-    let next_opcode = self.next_opcode();
+    if let Halt = opcode {
+      // Changes nothing, so stepping a halted computer keeps reporting it
+      // without counting steps or filling up the history
+      tracer.instruction(self, &opcode);
+      tracer.executed(&RunState::<W>::Halted);
+      return Ok(RunState::Halted);
+    }
+    if let Some(limit) = self.step_limit.filter(|&limit| self.steps >= limit) {
+      let (ip, instruction) = self.current();
+      return Err(IntcodeError::StepLimit { ip, instruction, limit });
+    }
+    let before = self.history.is_some().then(|| (self.instruction_pointer, self.relative_base.clone()));
+    let mut read = None;
+    let mut written = None;
+    if let Input { destination } = &opcode {
+      // Check where it's going first, so a bad destination doesn't eat the input
+      let addr = self.address(destination)?;
+      match self.input.read() {
+        Some(val) => {
+          written = Some((addr, val.clone()));
+          read = Some(val);
+        },
+        None => return Ok(RunState::NeedsInput),
+      }
+    }
+    tracer.instruction(self, &opcode);
     let mut next_instr = None;
-    match &next_opcode {
-      Ok(Add { left, right, store }) => {
-        let store = self.lookup(store) as Position;
-        self.write(store, self.lookup(left) + self.lookup(right));
+    let mut state = RunState::Running;
+    match &opcode {
+      Add { left, right, store } => {
+        written = Some((self.address(store)?, self.add(&self.lookup(left)?, &self.lookup(right)?)?));
       },
-      Ok(Mult { left, right, store, .. }) => {
-        let store = self.lookup(store) as Position;
-        self.write(store, self.lookup(left) * self.lookup(right));
+      Mult { left, right, store, .. } => {
+        let val = self.lookup(left)?.checked_mul(&self.lookup(right)?).ok_or_else(|| self.overflow())?;
+        written = Some((self.address(store)?, val));
       },
-      Ok(Input { destination }) => {
-        let destination = self.lookup(destination) as Position;
-        let val = self.input.pop_front().unwrap();
-        self.write(destination, val);
-      }
-      Ok(Output { source }) => {
-        self.output.push_back(self.lookup(source));
+      Input { .. } => {},
+      Output { source } => {
+        let val = self.lookup(source)?;
+        self.output.write(val.clone());
+        state = RunState::Output(val);
       },
-      Ok(JumpIfTrue { source, dest}) => {
-        let val = self.lookup(source);
-        let dest = self.lookup(dest);
-        if val != 0 {
-          next_instr = Some(dest as Position);
+      JumpIfTrue { source, dest} => {
+        let val = self.lookup(source)?;
+        let dest = self.lookup(dest)?;
+        if !val.is_zero() {
+          next_instr = Some(self.position(&dest)?);
         }
       },
-      Ok(JumpIfFalse { source, dest }) => {
-        let val = self.lookup(source);
-        let dest = self.lookup(dest);
-        if val == 0 {
-          next_instr = Some(dest as Position);
+      JumpIfFalse { source, dest } => {
+        let val = self.lookup(source)?;
+        let dest = self.lookup(dest)?;
+        if val.is_zero() {
+          next_instr = Some(self.position(&dest)?);
         }
       },
-      Ok(LessThan { left, right, store}) => {
-        let left = self.lookup(left);
-        let right = self.lookup(right);
-        let store = self.lookup(store) as Position;
-        let val = if left < right { 1 } else { 0 };
-        self.write(store, val);
+      LessThan { left, right, store} => {
+        let left = self.lookup(left)?;
+        let right = self.lookup(right)?;
+        let val = W::from_i64((left < right) as i64);
+        written = Some((self.address(store)?, val));
+      },
+      Equals { left, right, store} => {
+        let left = self.lookup(left)?;
+        let right = self.lookup(right)?;
+        let val = W::from_i64((left == right) as i64);
+        written = Some((self.address(store)?, val));
       },
-      Ok(Equals { left, right, store}) => {
-        let left = self.lookup(left);
-        let right = self.lookup(right);
-        let store = self.lookup(store) as Position;
-        let val = if left == right { 1 } else { 0 };
-        self.write(store, val);
+      AdjustRelativeBase { offset } => {
+        self.relative_base = self.add(&self.relative_base, &self.lookup(offset)?)?;
       },
-      Ok(Halt) => return Ok(false),
-      #[allow(unreachable_patterns)]
-      Ok(_) => { bail!("Unimplemented opcode"); },
-      Err(_) => { bail!("Unrecognized opcode"); },
+      Halt => unreachable!(),
+    }
+    let overwritten = written.as_ref()
+      .filter(|_| self.history.is_some())
+      .map(|(addr, _)| (*addr, self.memory.is_initialized(*addr).then(|| self.memory[*addr].clone())));
+    if let Some((addr, val)) = written {
+      tracer.write(addr, &self.memory[addr], &val);
+      self.write(addr, val);
     }
     if let Some(ip) = next_instr {
       self.instruction_pointer = ip;
-    } else if let Ok(opcode) = next_opcode {
+    } else {
       self.instruction_pointer += opcode.size();
     }
-    return Ok(true);
+    self.steps += 1;
+    if let (Some(history), Some((ip, relative_base))) = (&mut self.history, before) {
+      let output = match &state {
+        RunState::Output(val) => Some(val.clone()),
+        _ => None,
+      };
+      history.record(Undo { ip, relative_base, write: overwritten, input: read, output });
+    }
+    tracer.executed(&state);
+    Ok(state)
   }
-  pub fn run(&mut self) -> Result<()> {
-    while self.step()? {}
-    Ok(())
+  /// Runs until the next output, until blocked on input, or until halted.
+  pub fn run(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    self.run_with(&mut NoTrace)
   }
-
-  pub fn _debug(&mut self, limit: usize) -> Result<()> {
-    let mut limit = limit;
+  pub fn run_with<T: Tracer>(&mut self, tracer: &mut T) -> Result<RunState<W>, IntcodeError<W>> {
     loop {
-      println!("{}: {:?};  Next: {:?}", self.instruction_pointer, self.memory, self.next_opcode());
-      if !self.step()? {
-        println!("Halted.");
-        break;
+      match self.step_with(tracer)? {
+        RunState::Running => {},
+        state => return Ok(state),
       }
-      limit -= 1;
-      if limit == 0 {
-        println!("Diverged.");
-        bail!("Diverged.");
+    }
+  }
+  /// Runs past any outputs, leaving them to `output`, until blocked on input
+  /// or halted.
+  pub fn run_until_blocked(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    self.run_until_blocked_with(&mut NoTrace)
+  }
+  pub fn run_until_blocked_with<T: Tracer>(&mut self, tracer: &mut T) -> Result<RunState<W>, IntcodeError<W>> {
+    loop {
+      match self.run_with(tracer)? {
+        RunState::Output(_) => {},
+        state => return Ok(state),
       }
     }
-    Ok(())
   }
 }
 
-impl From<Vec<Value>> for Computer {
-    fn from(memory: Vec<Value>) -> Self {
-        Computer { instruction_pointer: 0, memory, input: VecDeque::new(), output: VecDeque::new() }
+impl<W: Word> From<Vec<W>> for Computer<W> {
+    fn from(memory: Vec<W>) -> Self {
+        Computer { instruction_pointer: 0, relative_base: W::default(), memory: memory.into(), input: VecDeque::new(), output: VecDeque::new(), strict: false, step_limit: None, steps: 0, history: None }
     }
 }
 
 #[cfg(test)]
 mod tests {
-  use matches::assert_matches;
+  use num::BigInt;
   use super::*;
 
-  #[test]
-  fn initialize_success() {
-    let comp: Computer = vec![0,1,2,3].into();
-    assert_eq!(1, comp.memory[1]);
-  }
+  // The whole suite, once for every word type
+  macro_rules! word_tests {
+    ($($name:ident: $word:ty),*) => {
+      $(mod $name {
+        use super::super::*;
 
-  #[test]
-  fn parse_opcode() {
-    use Parameter::*;
-    use Opcode::*;
-    let mut comp: Computer = vec![1,2,99,4,5].into();
-    assert_matches!(comp.next_opcode(), Ok(Add { left: Position(2), right: Position(99), store: Immediate(4) }));
-    comp.instruction_pointer += 1;
-    assert_matches!(comp.next_opcode(), Ok(Mult { left: Position(99), right: Position(4), store: Immediate(5) }));
-    comp.instruction_pointer += 1;
-    assert_matches!(comp.next_opcode(), Ok(Halt));
-  }
+        type W = $word;
 
-  #[test]
-  fn parse_param_mode() {
-    use Parameter::*;
-    use Opcode::*;
-    let mut comp: Computer = vec![1001,102,99,4,5].into();
-    assert_matches!(comp.next_opcode(), Ok(Add { left: Position(102), right: Immediate(99), store: Immediate(4) }));
-    comp.instruction_pointer += 1;
-    assert_matches!(comp.next_opcode(), Ok(Mult { left: Immediate(99), right: Position(4), store: Immediate(5) }));
-  }
+        fn w(value: i64) -> W {
+          W::from_i64(value)
+        }
 
-  #[test]
-  fn add_positional() {
-    let mut comp: Computer = vec![1,2,2,3].into();
-    assert!(comp.step().unwrap());
-    assert_eq!(4, comp.memory[3]);
-    assert_eq!(4, comp.instruction_pointer);
-  }
+        fn computer(program: &[i64]) -> Computer<W> {
+          program.iter().map(|&value| w(value)).collect::<Vec<_>>().into()
+        }
 
-  #[test]
-  fn add_immediate() {
-    let mut comp: Computer = vec![1101, 3, 3, 3].into();
-    assert!(comp.step().unwrap());
-    assert_eq!(6, comp.memory[3]);
-    assert_eq!(4, comp.instruction_pointer);
-
-    let mut comp: Computer = vec![1001, 0, 1, 3].into();
-    assert!(comp.step().unwrap());
-    assert_eq!(1002, comp.memory[3]);
-    assert_eq!(4, comp.instruction_pointer);
-  }
+        #[test]
+        fn initialize_success() {
+          let comp = computer(&[0,1,2,3]);
+          assert_eq!(w(1), comp.memory[1]);
+        }
 
-  #[test]
-  fn multiply_positional() {
-    let mut comp: Computer = vec![2,4,4,2,5].into();
-    assert!(comp.step().unwrap());
-    assert_eq!(25, comp.memory[2]);
-    assert_eq!(4, comp.instruction_pointer);
-  }
+        #[test]
+        fn parse_opcode() {
+          use Parameter::*;
+          use Opcode::*;
+          let mut comp = computer(&[1,2,99,4,5]);
+          assert_eq!(Ok(Add { left: Position(w(2)), right: Position(w(99)), store: Position(w(4)) }), comp.next_opcode());
+          comp.instruction_pointer += 1;
+          assert_eq!(Ok(Mult { left: Position(w(99)), right: Position(w(4)), store: Position(w(5)) }), comp.next_opcode());
+          comp.instruction_pointer += 1;
+          assert_eq!(Ok(Halt), comp.next_opcode());
+        }
 
-  #[test]
-  fn multiply_immediate() {
-    let mut comp: Computer = vec![1102, 3, 3, 3].into();
-    assert!(comp.step().unwrap());
-    assert_eq!(9, comp.memory[3]);
-    assert_eq!(4, comp.instruction_pointer);
-
-    let mut comp: Computer = vec![1002, 0, 1, 3].into();
-    assert!(comp.step().unwrap());
-    assert_eq!(1002, comp.memory[3]);
-    assert_eq!(4, comp.instruction_pointer);
-  }
+        #[test]
+        fn parse_param_mode() {
+          use Parameter::*;
+          use Opcode::*;
+          let mut comp = computer(&[1001,102,99,4,5]);
+          assert_eq!(Ok(Add { left: Position(w(102)), right: Immediate(w(99)), store: Position(w(4)) }), comp.next_opcode());
+          comp.instruction_pointer += 1;
+          assert_eq!(Ok(Mult { left: Immediate(w(99)), right: Position(w(4)), store: Position(w(5)) }), comp.next_opcode());
+        }
 
-  #[test]
-  fn halt() {
-    let mut comp: Computer = vec![99,1,2,3].into();
-    assert_eq!(false, comp.step().unwrap());
-  }
+        #[test]
+        fn add_positional() {
+          let mut comp = computer(&[1,2,2,3]);
+          assert_eq!(RunState::Running, comp.step().unwrap());
+          assert_eq!(w(4), comp.memory[3]);
+          assert_eq!(4, comp.instruction_pointer);
+        }
 
-  #[test]
-  fn simple_run() {
-    let mut comp: Computer = vec![1,1,1,4,99,4,4,0,99].into();
-    comp.run().unwrap();
-    assert_eq!(4, comp.memory[0]);
-  }
+        #[test]
+        fn add_immediate() {
+          let mut comp = computer(&[1101, 3, 3, 3]);
+          assert_eq!(RunState::Running, comp.step().unwrap());
+          assert_eq!(w(6), comp.memory[3]);
+          assert_eq!(4, comp.instruction_pointer);
+
+          let mut comp = computer(&[1001, 0, 1, 3]);
+          assert_eq!(RunState::Running, comp.step().unwrap());
+          assert_eq!(w(1002), comp.memory[3]);
+          assert_eq!(4, comp.instruction_pointer);
+        }
 
-  #[test]
-  fn input() {
-    let mut comp: Computer = vec![3, 3, 1101, 0, 9, 6, 0].into();
-    comp.input.extend(Some(90));
-    comp.run().unwrap();
-    assert_eq!(99, comp.memory[6]);
-  }
+        #[test]
+        fn multiply_positional() {
+          let mut comp = computer(&[2,4,4,2,5]);
+          assert_eq!(RunState::Running, comp.step().unwrap());
+          assert_eq!(w(25), comp.memory[2]);
+          assert_eq!(4, comp.instruction_pointer);
+        }
 
-  #[test]
-  fn output() {
-    let mut comp: Computer = vec![1101, 90, 9, 6, 4, 6, 0].into();
-    comp.run().unwrap();
-    assert_eq!(Some(99), comp.output.pop_front());
-  }
+        #[test]
+        fn multiply_immediate() {
+          let mut comp = computer(&[1102, 3, 3, 3]);
+          assert_eq!(RunState::Running, comp.step().unwrap());
+          assert_eq!(w(9), comp.memory[3]);
+          assert_eq!(4, comp.instruction_pointer);
+
+          let mut comp = computer(&[1002, 0, 1, 3]);
+          assert_eq!(RunState::Running, comp.step().unwrap());
+          assert_eq!(w(1002), comp.memory[3]);
+          assert_eq!(4, comp.instruction_pointer);
+        }
 
-  #[test]
-  fn jump_if_true() {
-    let mut comp: Computer = vec![5, 1, 4, 99, 4, 0, 99].into();
-    comp.run().unwrap();
-    assert_eq!(Some(5), comp.output.pop_front());
+        #[test]
+        fn halt() {
+          let mut comp = computer(&[99,1,2,3]);
+          assert_eq!(RunState::Halted, comp.step().unwrap());
+        }
+
+        #[test]
+        fn simple_run() {
+          let mut comp = computer(&[1,1,1,4,99,4,4,0,99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(w(4), comp.memory[0]);
+        }
+
+        #[test]
+        fn input() {
+          let mut comp = computer(&[3, 3, 1101, 0, 9, 6, 0]);
+          comp.input.extend(Some(w(90)));
+          comp.run_until_blocked().unwrap();
+          assert_eq!(w(99), comp.memory[6]);
+        }
+
+        #[test]
+        fn output() {
+          let mut comp = computer(&[1101, 90, 9, 6, 4, 6, 0]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(99)), comp.output.pop_front());
+        }
+
+        #[test]
+        fn jump_if_true() {
+          let mut comp = computer(&[5, 1, 4, 99, 4, 0, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(5)), comp.output.pop_front());
+        }
+
+        #[test]
+        fn jump_if_false() {
+          let mut comp = computer(&[6, 1, 5, 4, 0, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(6)), comp.output.pop_front());
+
+          let mut comp = computer(&[1106, 0, 4, 99, 4, 0, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(1106)), comp.output.pop_front());
+        }
+
+        #[test]
+        fn opcode_less_than() {
+          let mut comp = computer(&[7, 1, 0, 5, 4, 0, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(1)), comp.output.pop_front());
+
+          let mut comp = computer(&[1107, 1, 4, 5, 4, 0, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(1)), comp.output.pop_front());
+        }
+
+        #[test]
+        fn opcode_equals() {
+          let mut comp = computer(&[8, 1, 1, 5, 4, 0, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(1)), comp.output.pop_front());
+
+          let mut comp = computer(&[1108, 1, 4, 5, 4, 0, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(1108)), comp.output.pop_front());
+        }
+
+        #[test]
+        pub fn day5_example() {
+          let comp = computer(&[
+            3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,
+            1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,
+            999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99
+          ]);
+
+          let mut run1 = comp.clone();
+          run1.input.push_back(w(7));
+          run1.run_until_blocked().unwrap();
+          assert_eq!(Some(w(999)), run1.output.pop_back());
+
+          let mut run2 = comp.clone();
+          run2.input.push_back(w(8));
+          run2.run_until_blocked().unwrap();
+          assert_eq!(Some(w(1000)), run2.output.pop_back());
+
+          let mut run3 = comp.clone();
+          run3.input.push_back(w(9));
+          run3.run_until_blocked().unwrap();
+          assert_eq!(Some(w(1001)), run3.output.pop_back());
+        }
+
+        #[test]
+        fn parse_relative_mode() {
+          use Parameter::*;
+          use Opcode::*;
+          let comp = computer(&[22201, 1, 2, 3, 209, -7, 99]);
+          assert_eq!(Ok(Add { left: Relative(w(1)), right: Relative(w(2)), store: Relative(w(3)) }), comp.next_opcode());
+          let comp = computer(&[31101, 1, 2, 3]);
+          assert!(comp.next_opcode().is_err());
+        }
+
+        #[test]
+        fn relative_base() {
+          let mut comp = computer(&[109, 19, 204, -34, 99]);
+          comp.relative_base = w(2000);
+          comp.memory[1985] = w(42);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(w(2019), comp.relative_base);
+          assert_eq!(Some(w(42)), comp.output.pop_front());
+
+          // Writes go through the relative base too
+          let mut comp = computer(&[109, 10, 21101, 3, 4, 0, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(w(7), comp.memory[10]);
+        }
+
+        #[test]
+        fn memory_past_the_end() {
+          let mut comp = computer(&[1001, 100, 5, 1_000_000, 4, 1_000_000, 99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(w(5), comp.memory[1_000_000]);
+          assert_eq!(Some(w(5)), comp.output.pop_front());
+        }
+
+        #[test]
+        pub fn day9_examples() {
+          let quine = [109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+          let mut comp = computer(&quine);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(quine.iter().map(|&value| w(value)).collect::<Vec<_>>(), comp.output.into_iter().collect::<Vec<_>>());
+
+          let mut comp = computer(&[1102,34915192,34915192,7,4,7,99,0]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(16, comp.output.pop_front().unwrap().to_string().len());
+
+          let mut comp = computer(&[104,1125899906842624,99]);
+          comp.run_until_blocked().unwrap();
+          assert_eq!(Some(w(1125899906842624)), comp.output.pop_front());
+        }
+
+        #[test]
+        fn pauses_on_output() {
+          let mut comp = computer(&[104, 1, 104, 2, 99]);
+          assert_eq!(RunState::Output(w(1)), comp.run().unwrap());
+          assert_eq!(RunState::Output(w(2)), comp.run().unwrap());
+          assert_eq!(RunState::Halted, comp.run().unwrap());
+          assert_eq!(RunState::Halted, comp.run().unwrap());
+          assert_eq!(vec![w(1), w(2)], comp.output.into_iter().collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn resumes_after_input() {
+          let mut comp = computer(&[3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0]);
+          assert_eq!(RunState::NeedsInput, comp.run().unwrap());
+          assert_eq!(0, comp.instruction_pointer);
+          comp.input.push_back(w(3));
+          assert_eq!(RunState::NeedsInput, comp.run().unwrap());
+          assert_eq!(2, comp.instruction_pointer);
+          comp.input.push_back(w(4));
+          assert_eq!(RunState::Output(w(7)), comp.run().unwrap());
+          assert_eq!(RunState::Halted, comp.run_until_blocked().unwrap());
+        }
+
+        #[test]
+        fn errors_carry_location() {
+          use IntcodeError::*;
+          let mut comp = computer(&[1, -1, 0, 0, 99]);
+          assert_eq!(Err(NegativeAddress { ip: 0, instruction: w(1), address: w(-1) }), comp.step());
+          let mut comp = computer(&[1105, 1, -5]);
+          assert_eq!(Err(NegativeAddress { ip: 0, instruction: w(1105), address: w(-5) }), comp.step());
+          let mut comp = computer(&[104, 1, 30001, 0, 0, 0]);
+          assert_eq!(Err(UnknownMode { ip: 2, instruction: w(30001), mode: 3 }), comp.run_until_blocked());
+          let mut comp = computer(&[42]);
+          assert_eq!(Err(UnknownOpcode { ip: 0, instruction: w(42) }), comp.step());
+          assert_eq!("Unknown opcode 42 at 0", comp.step().unwrap_err().to_string());
+        }
+
+        #[test]
+        fn keeps_input_when_the_destination_is_bad() {
+          use IntcodeError::*;
+          let mut comp = computer(&[109, -5, 203, 0, 99]);
+          comp.input.push_back(w(7));
+          assert_eq!(Err(NegativeAddress { ip: 2, instruction: w(203), address: w(-5) }), comp.run_until_blocked());
+          assert_eq!(vec![w(7)], comp.input.iter().cloned().collect::<Vec<_>>());
+
+          let mut comp = computer(&[103, 0, 99]).strict();
+          comp.input.push_back(w(7));
+          assert_eq!(Err(ImmediateWrite { ip: 0, instruction: w(103) }), comp.step());
+          assert_eq!(Some(w(7)), comp.input.pop_front());
+        }
+
+        #[test]
+        fn strict_mode() {
+          use IntcodeError::*;
+          let lenient = computer(&[11101, 1, 2, 1, 99]);
+          assert_eq!(Ok(RunState::Halted), lenient.clone().run_until_blocked());
+          assert_eq!(Err(ImmediateWrite { ip: 0, instruction: w(11101) }), lenient.strict().step());
+
+          let quine = [109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+          let mut comp = computer(&quine).strict();
+          assert_eq!(Err(UninitializedRead { ip: 4, instruction: w(1001), address: 100 }), comp.run_until_blocked());
+
+          // Anything that stays inside its own memory runs just the same
+          let mut comp = computer(&[3,9,8,9,10,9,4,9,99,-1,8]).strict();
+          comp.input.push_back(w(8));
+          assert_eq!(Ok(RunState::Halted), comp.run_until_blocked());
+          assert_eq!(Some(w(1)), comp.output.pop_front());
+        }
+
+        #[test]
+        fn step_limit() {
+          let mut comp = computer(&[1105, 1, 0]).with_step_limit(100);
+          assert_eq!(Err(IntcodeError::StepLimit { ip: 0, instruction: w(1105), limit: 100 }), comp.run_until_blocked());
+          assert_eq!(100, comp.steps);
+
+          let mut comp = computer(&[104, 1, 99]).with_step_limit(2);
+          assert_eq!(Ok(RunState::Halted), comp.run_until_blocked());
+        }
+      })*
+    }
   }
 
-  #[test]
-  fn jump_if_false() {
-    let mut comp: Computer = vec![6, 1, 5, 4, 0, 99].into();
-    comp.run().unwrap();
-    assert_eq!(Some(6), comp.output.pop_front());
-
-    let mut comp: Computer = vec![1106, 0, 4, 99, 4, 0, 99].into();
-    comp.run().unwrap();
-    assert_eq!(Some(1106), comp.output.pop_front());
+  word_tests! {
+    isize_words: isize,
+    i64_words: i64,
+    i128_words: i128,
+    big_words: num::BigInt
   }
 
-  #[test]
-  fn opcode_less_than() {
-    let mut comp: Computer = vec![7, 1, 0, 5, 4, 0, 99].into();
-    comp.run().unwrap();
-    assert_eq!(Some(1), comp.output.pop_front());
-
-    let mut comp: Computer = vec![1107, 1, 4, 5, 4, 0, 99].into();
-    comp.run().unwrap();
-    assert_eq!(Some(1), comp.output.pop_front());
+  // Squares i64::MAX, then squares that
+  fn squarer<W: Word>() -> Computer<W> {
+    let program = vec![2, 9, 9, 9, 2, 9, 9, 9, 99, i64::MAX];
+    program.into_iter().map(W::from_i64).collect::<Vec<_>>().into()
   }
 
   #[test]
-  fn opcode_equals() {
-    let mut comp: Computer = vec![8, 1, 1, 5, 4, 0, 99].into();
-    comp.run().unwrap();
-    assert_eq!(Some(1), comp.output.pop_front());
-
-    let mut comp: Computer = vec![1108, 1, 4, 5, 4, 0, 99].into();
-    comp.run().unwrap();
-    assert_eq!(Some(1108), comp.output.pop_front());
+  fn overflow() {
+    use IntcodeError::Overflow;
+    assert_eq!(Err(Overflow { ip: 0, instruction: 2 }), squarer::<i64>().run_until_blocked());
+    assert_eq!(Err(Overflow { ip: 4, instruction: 2 }), squarer::<i128>().run_until_blocked());
+
+    let mut comp = squarer::<BigInt>();
+    assert_eq!(Ok(RunState::Halted), comp.run_until_blocked());
+    assert_eq!(BigInt::from(i64::MAX).pow(4), comp.memory[9]);
+
+    // Nothing is written when it overflows
+    let mut comp = squarer::<i128>();
+    assert!(comp.run_until_blocked().is_err());
+    assert_eq!(i64::MAX as i128 * i64::MAX as i128, comp.memory[9]);
   }
 
   #[test]
-  pub fn day5_example() {
-    let comp: Computer = vec![
-      3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,
-      1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,
-      999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99
-    ].into();
-
-    let mut run1 = comp.clone();
-    run1.input.push_back(7);
-    run1.run().unwrap();
-    assert_eq!(Some(999), run1.output.pop_back());
-
-    let mut run2 = comp.clone();
-    run2.input.push_back(8);
-    run2.run().unwrap();
-    assert_eq!(Some(1000), run2.output.pop_back());
-
-    let mut run3 = comp.clone();
-    run3.input.push_back(9);
-    run3.run().unwrap();
-    assert_eq!(Some(1001), run3.output.pop_back());
+  fn address_too_large() {
+    let mut comp = Computer::<i128>::from(vec![4, 1 << 70, 99]);
+    assert_eq!(
+      Err(IntcodeError::AddressTooLarge { ip: 0, instruction: 4, address: 1 << 70 }),
+      comp.run_until_blocked(),
+    );
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use anyhow::*;
+
+use super::{Computer, Position, RunState, Value};
+
+/// The address day 23 reserves for its NAT.
+pub const NAT_ADDRESS: Position = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+  pub source: Position,
+  pub dest: Position,
+  pub x: Value,
+  pub y: Value,
+}
+
+/// Something other than a computer listening on a network address.
+pub trait Device {
+  fn receive(&mut self, packet: Packet);
+  /// Called at the end of every round in which the network was idle; any
+  /// packets returned are sent from `address`.
+  fn on_idle(&mut self, _address: Position) -> Vec<Packet> {
+    vec![]
+  }
+}
+
+/// Remembers the last packet it received, and resends it to address 0 whenever
+/// the network goes idle.
+#[derive(Debug, Default)]
+pub struct Nat {
+  pub last: Option<Packet>,
+}
+
+impl Device for Nat {
+  fn receive(&mut self, packet: Packet) {
+    self.last = Some(packet);
+  }
+
+  fn on_idle(&mut self, address: Position) -> Vec<Packet> {
+    self.last.iter().map(|p| Packet { source: address, dest: 0, x: p.x, y: p.y }).collect()
+  }
+}
+
+/// Computers at addresses `0..nodes.len()`, exchanging `(dest, x, y)` output
+/// triples as packets.
+///
+/// The network runs in rounds: each computer in address order runs until it
+/// blocks, getting a `-1` if its input was empty, and only then is everything
+/// sent during the round delivered, in the order it was sent. That makes the
+/// packet trace the same from run to run.
+pub struct Network {
+  pub nodes: Vec<Computer>,
+  /// Every packet delivered so far, in delivery order.
+  pub trace: Vec<Packet>,
+  pub rounds: usize,
+  halted: Vec<bool>,
+  devices: BTreeMap<Position, Box<dyn Device>>,
+}
+
+impl Network {
+  /// Boots `size` copies of `program`, each given its address as its first input.
+  pub fn new(program: &Computer, size: usize) -> Self {
+    let nodes = (0..size).map(|address| {
+      let mut computer = program.clone();
+      computer.input.push_back(address as Value);
+      computer
+    }).collect();
+    Network { nodes, trace: vec![], rounds: 0, halted: vec![false; size], devices: BTreeMap::new() }
+  }
+
+  pub fn with_device(mut self, address: Position, device: Box<dyn Device>) -> Result<Self> {
+    if address < self.nodes.len() {
+      bail!("Address {} already belongs to a computer", address);
+    }
+    self.devices.insert(address, device);
+    Ok(self)
+  }
+
+  /// Runs a single round, returning whether the network was idle: every
+  /// running computer was waiting on an empty input, and nothing was sent.
+  pub fn round(&mut self) -> Result<bool> {
+    self.rounds += 1;
+    let mut idle = true;
+    let mut sent = vec![];
+    for (address, node) in self.nodes.iter_mut().enumerate() {
+      if self.halted[address] {
+        continue;
+      }
+      if node.input.is_empty() {
+        node.input.push_back(-1);
+      } else {
+        idle = false;
+      }
+      self.halted[address] = node.run_until_blocked()? == RunState::Halted;
+      while node.output.len() >= 3 {
+        let mut next = || node.output.pop_front().unwrap();
+        let (dest, x, y) = (next(), next(), next());
+        let dest = Position::try_from(dest)
+          .map_err(|_| anyhow!("Computer {} sent a packet to invalid address {}", address, dest))?;
+        sent.push(Packet { source: address, dest, x, y });
+      }
+      if self.halted[address] && !node.output.is_empty() {
+        bail!("Computer {} halted partway through sending a packet: {:?}", address, node.output);
+      }
+    }
+    idle &= sent.is_empty();
+    self.deliver(sent)?;
+
+    if idle {
+      let mut sent = vec![];
+      for (&address, device) in self.devices.iter_mut() {
+        sent.extend(device.on_idle(address));
+      }
+      self.deliver(sent)?;
+    }
+    Ok(idle)
+  }
+
+  /// Runs until a packet matching `done` is delivered, giving up after
+  /// `max_rounds`.
+  pub fn run_until(&mut self, max_rounds: usize, mut done: impl FnMut(&Packet) -> bool) -> Result<Packet> {
+    let mut checked = self.trace.len();
+    for _ in 0..max_rounds {
+      self.round()?;
+      if let Some(packet) = self.trace[checked..].iter().find(|p| done(p)) {
+        return Ok(*packet);
+      }
+      checked = self.trace.len();
+    }
+    bail!("No matching packet after {} rounds", max_rounds);
+  }
+
+  fn deliver(&mut self, packets: Vec<Packet>) -> Result<()> {
+    for packet in packets {
+      if let Some(node) = self.nodes.get_mut(packet.dest) {
+        node.input.push_back(packet.x);
+        node.input.push_back(packet.y);
+      } else if let Some(device) = self.devices.get_mut(&packet.dest) {
+        device.receive(packet);
+      } else {
+        bail!("Packet {:?} sent to an address nothing is listening on", packet);
+      }
+      self.trace.push(packet);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::assembler::assemble;
+
+  // Boots by sending (255, address, address * 10), then forwards every packet
+  // it receives to 255 with y incremented.
+  fn forwarder() -> Computer {
+    assemble("
+               INPUT -> [address]
+               OUTPUT #255
+               OUTPUT [address]
+               MULT [address], #10 -> [y]
+               OUTPUT [y]
+      wait:    INPUT -> [x]
+               EQUALS [x], #-1 -> [idle]
+               JUMPIFTRUE [idle], #wait
+               INPUT -> [y]
+               OUTPUT #255
+               OUTPUT [x]
+               ADD [y], #1 -> [y]
+               OUTPUT [y]
+               JUMPIFTRUE #1, #wait
+      address: DATA 0
+      x:       DATA 0
+      y:       DATA 0
+      idle:    DATA 0
+    ").unwrap().into()
+  }
+
+  #[test]
+  fn packet_trace() {
+    let mut network = Network::new(&forwarder(), 2).with_device(NAT_ADDRESS, Box::new(Nat::default())).unwrap();
+    let packet = network.run_until(10, |p| p.y == 12).unwrap();
+    assert_eq!(Packet { source: 0, dest: 255, x: 1, y: 12 }, packet);
+    assert_eq!(5, network.rounds);
+    let packet = |source, dest, x, y| Packet { source, dest, x, y };
+    assert_eq!(vec![
+      packet(0, 255, 0, 0),
+      packet(1, 255, 1, 10),
+      packet(255, 0, 1, 10),
+      packet(0, 255, 1, 11),
+      packet(255, 0, 1, 11),
+      packet(0, 255, 1, 12),
+    ], network.trace);
+  }
+
+  #[test]
+  fn idle_detection() {
+    let mut network = Network::new(&forwarder(), 3);
+    assert!(network.round().is_err());
+
+    let mut network = Network::new(&forwarder(), 3).with_device(NAT_ADDRESS, Box::new(Nat::default())).unwrap();
+    assert!(!network.round().unwrap());
+    assert!(network.round().unwrap());
+    assert!(!network.round().unwrap());
+  }
+
+  #[test]
+  fn unknown_addresses() {
+    let program: Computer = vec![104,7,104,1,104,2,99].into();
+    assert!(Network::new(&program, 1).round().is_err());
+    let program: Computer = vec![104,-1,104,1,104,2,99].into();
+    assert!(Network::new(&program, 1).round().is_err());
+    assert!(Network::new(&program, 1).with_device(0, Box::new(Nat::default())).is_err());
+  }
+
+  #[test]
+  fn truncated_packets() {
+    let program: Computer = vec![104,0,104,1,99].into();
+    assert!(Network::new(&program, 1).round().is_err());
+  }
+}
@@ -0,0 +1,133 @@
+use anyhow::*;
+
+use super::{Computer, RunState, Value};
+
+/// A chain of computers, each one's output wired to the next one's input. With
+/// `feedback`, the last stage's output is also wired back into the first.
+pub struct Pipeline {
+  pub stages: Vec<Computer>,
+  pub feedback: bool,
+}
+
+impl Pipeline {
+  pub fn new(stages: Vec<Computer>, feedback: bool) -> Self {
+    Pipeline { stages, feedback }
+  }
+
+  /// One copy of `program` per phase setting, each primed with its phase as
+  /// its first input.
+  pub fn with_phases(program: &Computer, phases: &[Value], feedback: bool) -> Self {
+    let stages = phases.iter().map(|&phase| {
+      let mut computer = program.clone();
+      computer.input.push_back(phase);
+      computer
+    }).collect();
+    Pipeline::new(stages, feedback)
+  }
+
+  /// Feeds `input` to the first stage and runs every stage in turn until they
+  /// have all halted, returning the last value the final stage produced.
+  pub fn run(&mut self, input: Value) -> Result<Value> {
+    if self.stages.is_empty() {
+      bail!("Pipeline has no stages");
+    }
+    self.stages[0].input.push_back(input);
+    let last_stage = self.stages.len() - 1;
+    let mut last_output = None;
+    loop {
+      let mut progressed = false;
+      let mut halted = true;
+      for i in 0..self.stages.len() {
+        let state = self.stages[i].run_until_blocked()?;
+        halted &= state == RunState::Halted;
+        let outputs: Vec<_> = self.stages[i].output.drain(..).collect();
+        progressed |= !outputs.is_empty();
+        if i < last_stage {
+          self.stages[i + 1].input.extend(outputs);
+        } else {
+          last_output = outputs.last().copied().or(last_output);
+          if self.feedback {
+            self.stages[0].input.extend(outputs);
+          }
+        }
+      }
+      if halted {
+        break;
+      }
+      if !progressed {
+        bail!("Pipeline is deadlocked: every running stage is waiting on input");
+      }
+    }
+    last_output.ok_or_else(|| anyhow!("The last stage never produced any output"))
+  }
+}
+
+/// Tries every ordering of `phases`, returning the one that gives the highest
+/// final output along with that output.
+pub fn best_phases(program: &Computer, phases: &[Value], feedback: bool) -> Result<(Vec<Value>, Value)> {
+  let mut best: Option<(Vec<Value>, Value)> = None;
+  for order in permutations(phases) {
+    let output = Pipeline::with_phases(program, &order, feedback).run(0)?;
+    if best.as_ref().is_none_or(|(_, b)| output > *b) {
+      best = Some((order, output));
+    }
+  }
+  best.ok_or_else(|| anyhow!("No phase settings to try"))
+}
+
+fn permutations(items: &[Value]) -> Vec<Vec<Value>> {
+  if items.len() <= 1 {
+    return vec![items.to_vec()];
+  }
+  let mut result = vec![];
+  for i in 0..items.len() {
+    let mut rest = items.to_vec();
+    let first = rest.remove(i);
+    for mut tail in permutations(&rest) {
+      tail.insert(0, first);
+      result.push(tail);
+    }
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn day7_examples() {
+    let program: Computer = vec![3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0].into();
+    assert_eq!(43210, Pipeline::with_phases(&program, &[4,3,2,1,0], false).run(0).unwrap());
+    assert_eq!((vec![4,3,2,1,0], 43210), best_phases(&program, &[0,1,2,3,4], false).unwrap());
+
+    let program: Computer = vec![
+      3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,
+      1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0
+    ].into();
+    assert_eq!((vec![1,0,4,3,2], 65210), best_phases(&program, &[0,1,2,3,4], false).unwrap());
+  }
+
+  #[test]
+  fn day7_feedback_examples() {
+    let program: Computer = vec![
+      3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,
+      27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5
+    ].into();
+    assert_eq!(139629729, Pipeline::with_phases(&program, &[9,8,7,6,5], true).run(0).unwrap());
+    assert_eq!((vec![9,8,7,6,5], 139629729), best_phases(&program, &[5,6,7,8,9], true).unwrap());
+  }
+
+  #[test]
+  fn deadlock() {
+    // Every stage wants two inputs before it will output anything
+    let program: Computer = vec![3,0,3,0,4,0,99].into();
+    let mut pipeline = Pipeline::new(vec![program.clone(), program], true);
+    assert!(pipeline.run(1).is_err());
+  }
+
+  #[test]
+  fn permutation_count() {
+    assert_eq!(120, permutations(&[0,1,2,3,4]).len());
+  }
+}
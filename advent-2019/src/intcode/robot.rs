@@ -0,0 +1,315 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::*;
+
+use super::{Computer, RunState, Value};
+
+/// A grid position. `y` grows downwards, so north is `y - 1` and grids render
+/// top to bottom.
+pub type Point = (i64, i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Heading {
+  North,
+  East,
+  South,
+  West,
+}
+
+impl Heading {
+  pub const ALL: [Heading; 4] = [Heading::North, Heading::East, Heading::South, Heading::West];
+
+  pub fn left(self) -> Heading {
+    Heading::ALL[(self as usize + 3) % 4]
+  }
+
+  pub fn right(self) -> Heading {
+    Heading::ALL[(self as usize + 1) % 4]
+  }
+
+  /// Turns the way a painting robot asks to: 0 for left, 1 for right.
+  pub fn turn(self, direction: Value) -> Result<Heading> {
+    match direction {
+      0 => Ok(self.left()),
+      1 => Ok(self.right()),
+      _ => bail!("Unknown turn {}", direction),
+    }
+  }
+
+  pub fn forward(self, (x, y): Point) -> Point {
+    match self {
+      Heading::North => (x, y - 1),
+      Heading::East => (x + 1, y),
+      Heading::South => (x, y + 1),
+      Heading::West => (x - 1, y),
+    }
+  }
+
+  /// The movement command a repair droid takes to go this way.
+  pub fn command(self) -> Value {
+    match self {
+      Heading::North => 1,
+      Heading::South => 2,
+      Heading::West => 3,
+      Heading::East => 4,
+    }
+  }
+}
+
+/// A sparse map of whatever has been seen so far; anything else is unknown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+  tiles: HashMap<Point, T>,
+}
+
+impl<T> Default for Grid<T> {
+  fn default() -> Self {
+    Grid { tiles: HashMap::new() }
+  }
+}
+
+impl<T> Grid<T> {
+  pub fn get(&self, point: Point) -> Option<&T> {
+    self.tiles.get(&point)
+  }
+
+  pub fn insert(&mut self, point: Point, tile: T) -> Option<T> {
+    self.tiles.insert(point, tile)
+  }
+
+  pub fn len(&self) -> usize {
+    self.tiles.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.tiles.is_empty()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+    self.tiles.iter().map(|(&point, tile)| (point, tile))
+  }
+
+  /// The top left and bottom right corners of everything seen, inclusive.
+  pub fn bounds(&self) -> Option<(Point, Point)> {
+    let xs = self.tiles.keys().map(|&(x, _)| x);
+    let ys = self.tiles.keys().map(|&(_, y)| y);
+    Some(((xs.clone().min()?, ys.clone().min()?), (xs.max()?, ys.max()?)))
+  }
+
+  /// Draws every point within the bounds, one line per row. `draw` is given
+  /// unknown points too, and the point itself so it can mark a robot.
+  pub fn render(&self, draw: impl Fn(Point, Option<&T>) -> char) -> String {
+    let ((left, top), (right, bottom)) = match self.bounds() {
+      Some(bounds) => bounds,
+      None => return String::new(),
+    };
+    let mut out = String::new();
+    for y in top..=bottom {
+      out.extend((left..=right).map(|x| draw((x, y), self.get((x, y)))));
+      out.push('\n');
+    }
+    out
+  }
+}
+
+/// A painting robot: it reads the colour of the panel it's on, then outputs a
+/// colour to paint it and which way to turn before moving forward one panel.
+/// Panels start out as 0.
+pub struct Robot {
+  pub computer: Computer,
+  pub position: Point,
+  pub heading: Heading,
+  pub panels: Grid<Value>,
+}
+
+impl Robot {
+  /// Starts at the origin, facing north.
+  pub fn new(computer: Computer) -> Self {
+    Robot { computer, position: (0, 0), heading: Heading::North, panels: Grid::default() }
+  }
+
+  /// Paints until the program halts.
+  pub fn run(&mut self) -> Result<()> {
+    loop {
+      let panel = self.panels.get(self.position).copied().unwrap_or(0);
+      self.computer.input.push_back(panel);
+      let state = self.computer.run_until_blocked()?;
+      while self.computer.output.len() >= 2 {
+        let colour = self.computer.output.pop_front().unwrap();
+        let turn = self.computer.output.pop_front().unwrap();
+        self.panels.insert(self.position, colour);
+        self.heading = self.heading.turn(turn)?;
+        self.position = self.heading.forward(self.position);
+      }
+      if state == RunState::Halted {
+        if !self.computer.output.is_empty() {
+          bail!("Robot halted with a colour but no turn: {:?}", self.computer.output);
+        }
+        return Ok(());
+      }
+    }
+  }
+}
+
+/// Everything a breadth first search of a droid's surroundings turned up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exploration {
+  /// The status of every tile the droid tried to move onto, where 0 is a wall.
+  /// The start is recorded as 1.
+  pub map: Grid<Value>,
+  /// How many moves from the start each open tile is.
+  pub distances: HashMap<Point, usize>,
+}
+
+impl Exploration {
+  /// The closest tile with the given status, and how many moves away it is.
+  pub fn nearest(&self, status: Value) -> Option<(Point, usize)> {
+    self.map.iter()
+      .filter(|&(_, &tile)| tile == status)
+      .filter_map(|(point, _)| Some((point, *self.distances.get(&point)?)))
+      .min_by_key(|&(point, distance)| (distance, point))
+  }
+}
+
+/// Maps out everything reachable by a repair droid, which takes a movement
+/// command and answers with 0 if it hit a wall, or otherwise moves and
+/// answers with what it found there. Each tile is explored from a clone of the
+/// computer that reached it, so the droid never has to walk back.
+pub fn explore(computer: &Computer) -> Result<Exploration> {
+  let mut map = Grid::default();
+  let mut distances = HashMap::new();
+  map.insert((0, 0), 1);
+  distances.insert((0, 0), 0);
+  let mut queue = VecDeque::new();
+  queue.push_back(((0, 0), computer.clone()));
+  while let Some((position, droid)) = queue.pop_front() {
+    let distance = distances[&position];
+    for &heading in Heading::ALL.iter() {
+      let next = heading.forward(position);
+      if map.get(next).is_some() {
+        continue;
+      }
+      let mut droid = droid.clone();
+      droid.output.clear();
+      droid.input.push_back(heading.command());
+      let status = match droid.run()? {
+        RunState::Output(status) => status,
+        state => bail!("Droid at {:?} stopped ({:?}) without saying whether it moved", position, state),
+      };
+      map.insert(next, status);
+      if status != 0 {
+        distances.insert(next, distance + 1);
+        queue.push_back((next, droid));
+      }
+    }
+  }
+  Ok(Exploration { map, distances })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::assembler::assemble;
+
+  // Flips the colour of the panel it's on and turns right, five times over
+  fn painter() -> Computer {
+    assemble("
+      loop: INPUT -> [c]
+            MULT [c], #-1 -> [c]
+            ADD [c], #1 -> [c]
+            OUTPUT [c]
+            OUTPUT #1
+            ADD [n], #-1 -> [n]
+            JUMPIFTRUE [n], #loop
+            HALT
+      c:    DATA 0
+      n:    DATA 5
+    ").unwrap().into()
+  }
+
+  // A droid in a small maze, starting at (1, 1):
+  //   #####
+  //   #..2#
+  //   #.#.#
+  //   #####
+  fn droid() -> Computer {
+    assemble("
+      loop: INPUT -> [cmd]
+            ADD [x], #0 -> [nx]
+            ADD [y], #0 -> [ny]
+            EQUALS [cmd], #1 -> [t]
+            MULT [t], #-1 -> [t]
+            ADD [ny], [t] -> [ny]
+            EQUALS [cmd], #2 -> [t]
+            ADD [ny], [t] -> [ny]
+            EQUALS [cmd], #3 -> [t]
+            MULT [t], #-1 -> [t]
+            ADD [nx], [t] -> [nx]
+            EQUALS [cmd], #4 -> [t]
+            ADD [nx], [t] -> [nx]
+            MULT [ny], #5 -> [i]
+            ADD [i], [nx] -> [i]
+            ADD [i], #maze -> [i]
+            ADJUSTRELATIVEBASE [i]
+            ADD [rb], #0 -> [cell]
+            MULT [i], #-1 -> [i]
+            ADJUSTRELATIVEBASE [i]
+            OUTPUT [cell]
+            JUMPIFFALSE [cell], #loop
+            ADD [nx], #0 -> [x]
+            ADD [ny], #0 -> [y]
+            JUMPIFTRUE #1, #loop
+      x:    DATA 1
+      y:    DATA 1
+      nx:   DATA 0
+      ny:   DATA 0
+      cmd:  DATA 0
+      t:    DATA 0
+      i:    DATA 0
+      cell: DATA 0
+      maze: DATA 0, 0, 0, 0, 0
+            DATA 0, 1, 1, 2, 0
+            DATA 0, 1, 0, 1, 0
+            DATA 0, 0, 0, 0, 0
+    ").unwrap().into()
+  }
+
+  #[test]
+  fn headings() {
+    assert_eq!(Heading::West, Heading::North.left());
+    assert_eq!(Heading::North, Heading::West.right());
+    assert_eq!(Heading::South, Heading::East.turn(1).unwrap());
+    assert!(Heading::East.turn(2).is_err());
+    assert_eq!((3, 4), Heading::South.forward((3, 3)));
+  }
+
+  #[test]
+  fn paints() {
+    let mut robot = Robot::new(painter());
+    robot.run().unwrap();
+    assert_eq!(4, robot.panels.len());
+    assert_eq!(Some(&0), robot.panels.get((0, 0)));
+    assert_eq!(((1, 0), Heading::East), (robot.position, robot.heading));
+    let drawing = robot.panels.render(|_, panel| if panel == Some(&1) { '#' } else { '.' });
+    assert_eq!(".#\n##\n", drawing);
+
+    let mut robot = Robot::new(vec![104, 1, 99].into());
+    assert!(robot.run().is_err());
+  }
+
+  #[test]
+  fn explores() {
+    let exploration = explore(&droid()).unwrap();
+    assert_eq!(Some(((2, 0), 2)), exploration.nearest(2));
+    assert_eq!(5, exploration.distances.len());
+    assert_eq!(Some(&3), exploration.distances.get(&(2, 1)));
+    let drawing = exploration.map.render(|point, tile| match (point, tile) {
+      ((0, 0), _) => 'D',
+      (_, None) => ' ',
+      (_, Some(0)) => '#',
+      (_, Some(2)) => 'O',
+      _ => '.',
+    });
+    assert_eq!(" ### \n#D.O#\n#.#.#\n # # \n", drawing);
+  }
+}
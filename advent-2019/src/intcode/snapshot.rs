@@ -0,0 +1,196 @@
+//! Saving a machine mid-run and picking it back up later. Snapshots are plain
+//! text, one field per line:
+//!
+//! ```text
+//! intcode-snapshot 2
+//! ip 12
+//! rb 2000
+//! steps 340
+//! input 1,2
+//! output 42
+//! image 3,100,...
+//! extra 1985=42,2019=7
+//! ```
+//!
+//! `extra` holds anything written past the end of the loaded image, and may be
+//! left out when there's nothing there. Version 1 snapshots have no `steps`,
+//! and load with it at 0.
+//!
+//! Only the machine's state is saved, not how it's being run: a loaded machine
+//! isn't strict and has no step limit or history, whatever the saved one had.
+//! Use `strict`, `with_step_limit` and `with_history` to set them up again.
+
+use std::{error::Error, fs, path::Path, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use advent_shared::input::read_input;
+
+use super::{Computer, Memory, Position, Word};
+
+pub const VERSION: u32 = 2;
+const MAGIC: &str = "intcode-snapshot";
+
+impl<W: Word> Computer<W> {
+  pub fn to_snapshot(&self) -> String {
+    let mut snapshot = format!("{} {}\n", MAGIC, VERSION);
+    snapshot += &format!("ip {}\n", self.instruction_pointer);
+    snapshot += &format!("rb {}\n", self.relative_base);
+    snapshot += &format!("steps {}\n", self.steps);
+    snapshot += &format!("input {}\n", join(&self.input));
+    snapshot += &format!("output {}\n", join(&self.output));
+    snapshot += &format!("image {}\n", join(self.memory.image()));
+    let extra = self.memory.extra();
+    if !extra.is_empty() {
+      snapshot += &format!("extra {}\n", join(extra.iter().map(|(addr, val)| format!("{}={}", addr, val))));
+    }
+    snapshot
+  }
+
+  pub fn from_snapshot(snapshot: &str) -> Result<Computer<W>>
+  where <W as FromStr>::Err: Error + Send + Sync + 'static {
+    let mut lines = snapshot.lines().enumerate();
+    let version = match lines.next().map(|(_, header)| header.split_once(' ')) {
+      Some(Some((MAGIC, version))) => match version.parse::<u32>() {
+        Ok(version @ 1..=VERSION) => version,
+        _ => bail!("Unsupported snapshot version {} (expected {})", version, VERSION),
+      },
+      _ => bail!("Not an intcode snapshot"),
+    };
+
+    let (mut ip, mut rb, mut steps, mut input, mut output, mut image) = (None, None, None, None, None, None);
+    let mut extra = vec![];
+    for (i, line) in lines {
+      let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+      let context = || format!("line {}: invalid {}", i + 1, key);
+      match key {
+        "ip" => ip = Some(value.parse::<Position>().with_context(context)?),
+        "rb" => rb = Some(value.parse::<W>().with_context(context)?),
+        "steps" => steps = Some(value.parse::<usize>().with_context(context)?),
+        "input" => input = Some(list::<W>(value).with_context(context)?),
+        "output" => output = Some(list::<W>(value).with_context(context)?),
+        "image" => image = Some(list::<W>(value).with_context(context)?),
+        "extra" => {
+          for cell in value.split(',') {
+            let (addr, val) = cell.split_once('=').ok_or_else(|| anyhow!("{}: expected address=value", context()))?;
+            extra.push((addr.parse::<Position>().with_context(context)?, val.parse::<W>().with_context(context)?));
+          }
+        },
+        "" => {},
+        _ => bail!("line {}: unknown field {}", i + 1, key),
+      }
+    }
+
+    let missing = |field: &str| anyhow!("Snapshot is missing {}", field);
+    let steps = match (steps, version) {
+      (Some(steps), _) => steps,
+      (None, 1) => 0,
+      (None, _) => bail!(missing("steps")),
+    };
+    let mut memory: Memory<W> = image.ok_or_else(|| missing("image"))?.into();
+    for (addr, val) in extra {
+      memory[addr] = val;
+    }
+    Ok(Computer {
+      instruction_pointer: ip.ok_or_else(|| missing("ip"))?,
+      relative_base: rb.ok_or_else(|| missing("rb"))?,
+      memory,
+      input: input.ok_or_else(|| missing("input"))?.into(),
+      output: output.ok_or_else(|| missing("output"))?.into(),
+      strict: false,
+      step_limit: None,
+      steps,
+      history: None,
+    })
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+    Ok(fs::write(path, self.to_snapshot())?)
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Result<Computer<W>>
+  where <W as FromStr>::Err: Error + Send + Sync + 'static {
+    Computer::from_snapshot(&read_input(path.as_ref())?)
+  }
+}
+
+fn join<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+  values.into_iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn list<T: FromStr>(value: &str) -> Result<Vec<T>, T::Err> {
+  value.split(',').filter(|v| !v.is_empty()).map(str::parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use num::BigInt;
+  use crate::intcode::{RunState, Value};
+
+  // Echoes each input doubled, using relative addressing far past the image
+  fn doubler() -> Computer {
+    vec![109, 2000, 203, 0, 1002, 2000, 2, 2001, 204, 1, 1105, 1, 2].into()
+  }
+
+  #[test]
+  fn round_trips() {
+    let mut computer = doubler();
+    computer.input.extend(vec![5, 6]);
+    assert_eq!(RunState::Output(10), computer.run().unwrap());
+    let snapshot = computer.to_snapshot();
+    assert_eq!(
+      "intcode-snapshot 2\nip 10\nrb 2000\nsteps 4\ninput 6\noutput 10\nimage 109,2000,203,0,1002,2000,2,2001,204,1,1105,1,2\nextra 2000=5,2001=10\n",
+      snapshot
+    );
+
+    let mut restored: Computer = Computer::from_snapshot(&snapshot).unwrap();
+    assert_eq!(computer.memory, restored.memory);
+    for machine in [&mut computer, &mut restored].iter_mut() {
+      machine.input.push_back(7);
+      assert_eq!(RunState::NeedsInput, machine.run_until_blocked().unwrap());
+    }
+    assert_eq!(vec![10, 12, 14], restored.output.iter().copied().collect::<Vec<_>>());
+    assert_eq!(computer.to_snapshot(), restored.to_snapshot());
+  }
+
+  #[test]
+  fn any_word() {
+    let mut computer: Computer<BigInt> = vec![1002, 5, 2, 5, 99, 1].into_iter().map(BigInt::from).collect::<Vec<_>>().into();
+    computer.memory[5] = BigInt::from(i64::MAX).pow(2u32);
+    computer.run_until_blocked().unwrap();
+    let restored = Computer::<BigInt>::from_snapshot(&computer.to_snapshot()).unwrap();
+    assert_eq!(computer.memory, restored.memory);
+    assert_eq!(1, restored.steps);
+    assert!(Computer::<i64>::from_snapshot(&computer.to_snapshot()).is_err());
+  }
+
+  #[test]
+  fn loads_version_1() {
+    let computer: Computer = Computer::from_snapshot("intcode-snapshot 1\nip 0\nrb 0\ninput\noutput\nimage 99").unwrap();
+    assert_eq!(0, computer.steps);
+    assert_eq!(RunState::Halted, computer.clone().run_until_blocked().unwrap());
+  }
+
+  #[test]
+  fn saves_to_disk() {
+    let path = std::env::temp_dir().join(format!("advent-2019-snapshot-{}.txt", std::process::id()));
+    let computer = doubler();
+    computer.save(&path).unwrap();
+    let loaded: Computer = Computer::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+    assert_eq!(computer.to_snapshot(), loaded.to_snapshot());
+  }
+
+  #[test]
+  fn rejects_bad_snapshots() {
+    let error = |s| Computer::<Value>::from_snapshot(s).unwrap_err().to_string();
+    assert_eq!("Unsupported snapshot version 3 (expected 2)", error("intcode-snapshot 3\n"));
+    assert_eq!("Unsupported snapshot version 0 (expected 2)", error("intcode-snapshot 0\n"));
+    assert_eq!("Not an intcode snapshot", error("1,2,3"));
+    assert_eq!("Snapshot is missing rb", error("intcode-snapshot 2\nip 0\nsteps 0\ninput\noutput\nimage 99"));
+    assert_eq!("Snapshot is missing steps", error("intcode-snapshot 2\nip 0\nrb 0\ninput\noutput\nimage 99"));
+    assert_eq!("line 3: invalid rb", error("intcode-snapshot 2\nip 0\nrb x\n"));
+    assert_eq!("line 2: unknown field pc", error("intcode-snapshot 2\npc 0\n"));
+  }
+}
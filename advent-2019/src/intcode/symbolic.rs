@@ -0,0 +1,434 @@
+//! Runs a program with some memory cells left as unknowns, so that what it
+//! computes comes out as a formula in those unknowns instead of a number.
+//! This only works as long as the program's control flow never depends on
+//! them; as soon as it would, the run fails and the caller has to try concrete
+//! values instead.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::ops::Range;
+
+use super::{IntcodeError, Opcode, Parameter, Position, RunState, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+  Const(Value),
+  /// The initial contents of this memory cell.
+  Var(Position),
+  Add(Box<Expr>, Box<Expr>),
+  Mul(Box<Expr>, Box<Expr>),
+  LessThan(Box<Expr>, Box<Expr>),
+  Equals(Box<Expr>, Box<Expr>),
+  /// Whatever was in memory at an address that depends on a variable.
+  Load(Box<Expr>),
+}
+
+impl Expr {
+  /// `left + right`, or `None` if they're both constants and adding them
+  /// overflows.
+  pub fn sum(left: Expr, right: Expr) -> Option<Expr> {
+    Some(match (left, right) {
+      (Expr::Const(a), Expr::Const(b)) => Expr::Const(a.checked_add(b)?),
+      (Expr::Const(0), other) | (other, Expr::Const(0)) => other,
+      (left, right) => Expr::Add(Box::new(left), Box::new(right)),
+    })
+  }
+
+  /// `left * right`, or `None` if they're both constants and multiplying them
+  /// overflows.
+  pub fn product(left: Expr, right: Expr) -> Option<Expr> {
+    Some(match (left, right) {
+      (Expr::Const(a), Expr::Const(b)) => Expr::Const(a.checked_mul(b)?),
+      (Expr::Const(0), _) | (_, Expr::Const(0)) => Expr::Const(0),
+      (Expr::Const(1), other) | (other, Expr::Const(1)) => other,
+      (left, right) => Expr::Mul(Box::new(left), Box::new(right)),
+    })
+  }
+
+  pub fn less_than(left: Expr, right: Expr) -> Option<Expr> {
+    Some(match (left, right) {
+      (Expr::Const(a), Expr::Const(b)) => Expr::Const((a < b) as Value),
+      (left, right) => Expr::LessThan(Box::new(left), Box::new(right)),
+    })
+  }
+
+  pub fn equals(left: Expr, right: Expr) -> Option<Expr> {
+    Some(match (left, right) {
+      (Expr::Const(a), Expr::Const(b)) => Expr::Const((a == b) as Value),
+      (left, right) => Expr::Equals(Box::new(left), Box::new(right)),
+    })
+  }
+
+  /// The expression as a constant plus a multiple of each variable, if it's
+  /// that simple and none of the coefficients overflow.
+  pub fn linear(&self) -> Option<Linear> {
+    match self {
+      Expr::Const(value) => Some(Linear { constant: *value, terms: BTreeMap::new() }),
+      Expr::Var(var) => Some(Linear { constant: 0, terms: vec![(*var, 1)].into_iter().collect() }),
+      Expr::Add(left, right) => {
+        let (mut sum, right) = (left.linear()?, right.linear()?);
+        sum.constant = sum.constant.checked_add(right.constant)?;
+        for (var, k) in right.terms {
+          let total = sum.terms.entry(var).or_default();
+          *total = total.checked_add(k)?;
+        }
+        sum.terms.retain(|_, k| *k != 0);
+        Some(sum)
+      },
+      Expr::Mul(left, right) => {
+        let (left, right) = (left.linear()?, right.linear()?);
+        let (scale, mut product) = match (left.terms.is_empty(), right.terms.is_empty()) {
+          (true, _) => (left.constant, right),
+          (_, true) => (right.constant, left),
+          _ => return None,
+        };
+        product.constant = product.constant.checked_mul(scale)?;
+        for k in product.terms.values_mut() {
+          *k = k.checked_mul(scale)?;
+        }
+        product.terms.retain(|_, k| *k != 0);
+        Some(product)
+      },
+      Expr::LessThan(..) | Expr::Equals(..) | Expr::Load(_) => None,
+    }
+  }
+}
+
+impl fmt::Display for Expr {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Expr::Const(value) => write!(f, "{}", value),
+      Expr::Var(address) => write!(f, "x{}", address),
+      Expr::Add(left, right) => write!(f, "({} + {})", left, right),
+      Expr::Mul(left, right) => write!(f, "{} * {}", left, right),
+      Expr::LessThan(left, right) => write!(f, "({} < {})", left, right),
+      Expr::Equals(left, right) => write!(f, "({} == {})", left, right),
+      Expr::Load(address) => write!(f, "[{}]", address),
+    }
+  }
+}
+
+/// `constant + sum(k * var)`, with no zero coefficients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Linear {
+  pub constant: Value,
+  pub terms: BTreeMap<Position, Value>,
+}
+
+impl Linear {
+  fn coefficient(&self, var: Position) -> Value {
+    self.terms.get(&var).copied().unwrap_or(0)
+  }
+
+  /// Values for the variables in `ranges` that make this equal `target`. All
+  /// but the last variable are tried in order, so the first solution found is
+  /// the same one a nested loop over the ranges would find, while the last is
+  /// solved for directly.
+  pub fn solve(&self, target: Value, ranges: &[(Position, Range<Value>)]) -> Option<BTreeMap<Position, Value>> {
+    if self.terms.keys().any(|var| !ranges.iter().any(|(v, _)| v == var)) {
+      return None;
+    }
+    let mut solution = BTreeMap::new();
+    if self.search(target.checked_sub(self.constant)?, ranges, &mut solution) {
+      Some(solution)
+    } else {
+      None
+    }
+  }
+
+  fn search(&self, rest: Value, ranges: &[(Position, Range<Value>)], solution: &mut BTreeMap<Position, Value>) -> bool {
+    match ranges {
+      [] => rest == 0,
+      [(var, range)] => {
+        let k = self.coefficient(*var);
+        let value = match k {
+          0 if rest == 0 => range.start,
+          0 => return false,
+          k if rest.checked_rem(k) == Some(0) => match rest.checked_div(k) {
+            Some(value) => value,
+            None => return false,
+          },
+          _ => return false,
+        };
+        solution.insert(*var, value);
+        range.contains(&value)
+      },
+      [(var, range), others @ ..] => {
+        let k = self.coefficient(*var);
+        range.clone().any(|value| {
+          solution.insert(*var, value);
+          // Anything that overflows is too far off to ever reach the target
+          match k.checked_mul(value).and_then(|kv| rest.checked_sub(kv)) {
+            Some(rest) => self.search(rest, others, solution),
+            None => false,
+          }
+        })
+      },
+    }
+  }
+}
+
+/// Why a symbolic run stopped short.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolicError {
+  /// Something that would go wrong on a `Computer` too.
+  Intcode(IntcodeError),
+  /// Carrying on needs `what` to be a number, but it's a formula.
+  Unknown { ip: Position, what: &'static str, expr: Expr },
+}
+
+impl From<IntcodeError> for SymbolicError {
+  fn from(error: IntcodeError) -> Self {
+    SymbolicError::Intcode(error)
+  }
+}
+
+impl fmt::Display for SymbolicError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SymbolicError::Intcode(error) => write!(f, "{}", error),
+      SymbolicError::Unknown { ip, what, expr } => write!(f, "{} at {} depends on {}", what, ip, expr),
+    }
+  }
+}
+
+impl std::error::Error for SymbolicError {}
+
+/// A cut-down `Computer` whose memory holds expressions. Outputs are collected
+/// rather than stopping the run, since they needn't be numbers.
+#[derive(Debug, Clone)]
+pub struct SymbolicComputer {
+  pub instruction_pointer: Position,
+  pub relative_base: Value,
+  memory: HashMap<Position, Expr>,
+  pub input: VecDeque<Value>,
+  pub output: Vec<Expr>,
+}
+
+impl SymbolicComputer {
+  pub fn new(image: &[Value]) -> Self {
+    SymbolicComputer {
+      instruction_pointer: 0,
+      relative_base: 0,
+      memory: image.iter().enumerate().map(|(addr, &val)| (addr, Expr::Const(val))).collect(),
+      input: VecDeque::new(),
+      output: vec![],
+    }
+  }
+
+  /// Makes the cell at `address` a variable.
+  pub fn symbolic(mut self, address: Position) -> Self {
+    self.memory.insert(address, Expr::Var(address));
+    self
+  }
+
+  pub fn memory(&self, address: Position) -> Expr {
+    self.memory.get(&address).cloned().unwrap_or(Expr::Const(0))
+  }
+
+  /// The value of `expr`, for the `what` of an instruction, which has to be
+  /// known for the run to carry on.
+  fn known(&self, expr: Expr, what: &'static str) -> Result<Value, SymbolicError> {
+    match expr {
+      Expr::Const(value) => Ok(value),
+      expr => Err(SymbolicError::Unknown { ip: self.instruction_pointer, what, expr }),
+    }
+  }
+
+  /// The instruction being executed, which is known by the time anything can
+  /// go wrong with it.
+  fn current(&self) -> (Position, Value) {
+    let ip = self.instruction_pointer;
+    (ip, self.known(self.memory(ip), "Instruction").unwrap_or_default())
+  }
+
+  /// Fails the run if some arithmetic overflowed, as `Computer` would.
+  fn checked<T>(&self, result: Option<T>) -> Result<T, SymbolicError> {
+    let (ip, instruction) = self.current();
+    result.ok_or(SymbolicError::Intcode(IntcodeError::Overflow { ip, instruction }))
+  }
+
+  fn position(&self, address: Expr, what: &'static str) -> Result<Position, SymbolicError> {
+    match self.known(address, what)? {
+      address if address >= 0 => Ok(address as Position),
+      address => {
+        let (ip, instruction) = self.current();
+        Err(IntcodeError::NegativeAddress { ip, instruction, address }.into())
+      },
+    }
+  }
+
+  /// The address a parameter refers to, for parameters that are written
+  /// through. Immediate mode stores write through the raw value, as
+  /// `Computer` does.
+  fn address(&self, param: &Parameter<Expr>) -> Result<Expr, SymbolicError> {
+    match param {
+      Parameter::Position(address) | Parameter::Immediate(address) => Ok(address.clone()),
+      Parameter::Relative(offset) => self.checked(Expr::sum(offset.clone(), Expr::Const(self.relative_base))),
+    }
+  }
+
+  fn read(&self, param: &Parameter<Expr>) -> Result<Expr, SymbolicError> {
+    if let Parameter::Immediate(value) = param {
+      return Ok(value.clone());
+    }
+    match self.address(param)? {
+      address @ Expr::Const(_) => Ok(self.memory(self.position(address, "Read")?)),
+      address => Ok(Expr::Load(Box::new(address))),
+    }
+  }
+
+  fn write(&mut self, param: &Parameter<Expr>, value: Expr) -> Result<(), SymbolicError> {
+    let address = self.position(self.address(param)?, "Write address")?;
+    self.memory.insert(address, value);
+    Ok(())
+  }
+
+  pub fn step(&mut self) -> Result<RunState, SymbolicError> {
+    use Opcode::*;
+    let ip = self.instruction_pointer;
+    let instruction = self.known(self.memory(ip), "Instruction")?;
+    let opcode = Opcode::decode(ip, &instruction, |n| self.memory(ip + n))?;
+    let mut next_instr = None;
+    match &opcode {
+      Add { left, right, store } => self.binary(Expr::sum, left, right, store)?,
+      Mult { left, right, store } => self.binary(Expr::product, left, right, store)?,
+      LessThan { left, right, store } => self.binary(Expr::less_than, left, right, store)?,
+      Equals { left, right, store } => self.binary(Expr::equals, left, right, store)?,
+      Input { destination } => {
+        // Check the destination before taking anything from `input`
+        self.position(self.address(destination)?, "Write address")?;
+        match self.input.pop_front() {
+          Some(value) => self.write(destination, Expr::Const(value))?,
+          None => return Ok(RunState::NeedsInput),
+        }
+      },
+      Output { source } => {
+        let value = self.read(source)?;
+        self.output.push(value);
+      },
+      JumpIfTrue { source, dest } | JumpIfFalse { source, dest } => {
+        let condition = self.known(self.read(source)?, "Jump condition")?;
+        if (condition != 0) == matches!(opcode, JumpIfTrue { .. }) {
+          next_instr = Some(self.position(self.read(dest)?, "Jump target")?);
+        }
+      },
+      AdjustRelativeBase { offset } => {
+        let offset = self.known(self.read(offset)?, "Relative base")?;
+        self.relative_base = self.checked(self.relative_base.checked_add(offset))?;
+      },
+      Halt => return Ok(RunState::Halted),
+    }
+    self.instruction_pointer = next_instr.unwrap_or(ip + opcode.size());
+    Ok(RunState::Running)
+  }
+
+  fn binary(
+    &mut self,
+    op: fn(Expr, Expr) -> Option<Expr>,
+    left: &Parameter<Expr>,
+    right: &Parameter<Expr>,
+    store: &Parameter<Expr>,
+  ) -> Result<(), SymbolicError> {
+    let value = self.checked(op(self.read(left)?, self.read(right)?))?;
+    self.write(store, value)
+  }
+
+  /// Runs until halted or blocked on input.
+  pub fn run(&mut self) -> Result<RunState, SymbolicError> {
+    loop {
+      match self.step()? {
+        RunState::Running => {},
+        state => return Ok(state),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::{assembler::assemble, Computer};
+
+  #[test]
+  fn builds_formulas() {
+    let program = assemble("
+         ADD [a], [b] -> [out]
+         MULT [out], #3 -> [out]
+         ADD [out], #-4 -> [out]
+         LESSTHAN [a], #5 -> [flag]
+         HALT
+      a: DATA 0
+      b: DATA 0
+    out: DATA 0
+   flag: DATA 0
+    ").unwrap();
+    let mut computer = SymbolicComputer::new(&program).symbolic(17).symbolic(18);
+    assert_eq!(RunState::Halted, computer.run().unwrap());
+    assert_eq!("((x17 + x18) * 3 + -4)", computer.memory(19).to_string());
+    let formula = computer.memory(19).linear().unwrap();
+    assert_eq!(-4, formula.constant);
+    assert_eq!(vec![(17, 3), (18, 3)], formula.terms.into_iter().collect::<Vec<_>>());
+    assert_eq!(None, computer.memory(20).linear());
+  }
+
+  #[test]
+  fn solves_for_a_target() {
+    // Like day 2: the noun and verb are addresses for the first instruction,
+    // whose result is thrown away, and values after that
+    let program = vec![1,0,0,3, 1,1,2,3, 2,3,13,0, 99, 5];
+    let mut computer = SymbolicComputer::new(&program).symbolic(1).symbolic(2);
+    assert_eq!(RunState::Halted, computer.run().unwrap());
+    let formula = computer.memory(0).linear().unwrap();
+    let solution = formula.solve(50, &[(1, 0..100), (2, 0..100)]).unwrap();
+    assert_eq!(vec![(1, 0), (2, 10)], solution.into_iter().collect::<Vec<_>>());
+    assert_eq!(None, formula.solve(51, &[(1, 0..100), (2, 0..100)]));
+    assert_eq!(None, formula.solve(50, &[(1, 20..100), (2, 0..100)]));
+    assert_eq!(None, formula.solve(50, &[(1, 0..100)]));
+
+    let mut concrete: Computer = program.into();
+    concrete.memory[2] = 10;
+    concrete.run_until_blocked().unwrap();
+    assert_eq!(50, concrete.memory[0]);
+  }
+
+  #[test]
+  fn stops_when_control_flow_depends_on_a_variable() {
+    let program = assemble("
+         JUMPIFTRUE [a], #end
+         OUTPUT #1
+    end: HALT
+      a: DATA 1
+    ").unwrap();
+    let mut computer = SymbolicComputer::new(&program);
+    assert_eq!(RunState::Halted, computer.run().unwrap());
+    let mut computer = SymbolicComputer::new(&program).symbolic(6);
+    assert_eq!("Jump condition at 0 depends on x6", computer.run().unwrap_err().to_string());
+
+    let mut computer = SymbolicComputer::new(&[1, 0, 0, 5, 99, 0]).symbolic(3);
+    assert!(computer.run().is_err());
+  }
+
+  #[test]
+  fn faults_match_the_interpreter() {
+    for program in [vec![42], vec![30001, 0, 0, 0], vec![1, -1, 0, 0, 99], vec![109, -5, 22201, 0, 0, 0]].iter() {
+      let expected = Computer::from(program.clone()).run_until_blocked().unwrap_err();
+      assert_eq!(Err(SymbolicError::Intcode(expected)), SymbolicComputer::new(program).run());
+    }
+  }
+
+  #[test]
+  fn overflow_fails_the_run() {
+    for &opcode in [1101, 1102].iter() {
+      let mut computer = SymbolicComputer::new(&[opcode, Value::MAX, 2, 0, 99]);
+      let overflow = IntcodeError::Overflow { ip: 0, instruction: opcode };
+      assert_eq!(Err(SymbolicError::Intcode(overflow)), computer.run());
+    }
+    let huge = Expr::product(Expr::Var(1), Expr::Const(Value::MAX)).unwrap();
+    assert_eq!(None, Expr::product(huge.clone(), Expr::Const(2)).unwrap().linear());
+    assert_eq!(None, Expr::sum(huge.clone(), huge.clone()).unwrap().linear());
+    let formula = huge.linear().unwrap();
+    assert_eq!(None, formula.solve(Value::MIN, &[(1, -2..2)]));
+    assert_eq!(None, formula.solve(1, &[(1, -2..2), (2, 0..1)]));
+  }
+}
@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::{Computer, Opcode, Position, RunState, Word};
+
+/// Hooks into every instruction `Computer::step_with` executes. Every hook
+/// does nothing by default, and plain `step` uses `NoTrace`, so untraced runs
+/// compile down to the same code as before.
+pub trait Tracer {
+  /// Called with the machine as it is just before `opcode` executes.
+  fn instruction<W: Word, I, O>(&mut self, _computer: &Computer<W, I, O>, _opcode: &Opcode<W>) {}
+  fn write<W: Word>(&mut self, _address: Position, _old: &W, _new: &W) {}
+  /// Called once the instruction has finished.
+  fn executed<W: Word>(&mut self, _state: &RunState<W>) {}
+}
+
+pub struct NoTrace;
+
+impl Tracer for NoTrace {}
+
+/// Writes one line per instruction: its address, mnemonic and the values of
+/// the operands it read, then the write it made, if any. For example
+/// `12 ADD 3 4 [40]=7`.
+pub struct TraceWriter<W: Write> {
+  out: W,
+  line: String,
+  error: Option<io::Error>,
+}
+
+impl TraceWriter<BufWriter<File>> {
+  pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+    Ok(TraceWriter::new(BufWriter::new(File::create(path)?)))
+  }
+}
+
+impl<W: Write> TraceWriter<W> {
+  pub fn new(out: W) -> Self {
+    TraceWriter { out, line: String::new(), error: None }
+  }
+
+  /// Flushes the trace, reporting the first error hit while writing it.
+  pub fn finish(mut self) -> io::Result<W> {
+    if let Some(e) = self.error {
+      return Err(e);
+    }
+    self.out.flush()?;
+    Ok(self.out)
+  }
+}
+
+impl<W: Write> Tracer for TraceWriter<W> {
+  fn instruction<V: Word, I, O>(&mut self, computer: &Computer<V, I, O>, opcode: &Opcode<V>) {
+    self.line.clear();
+    write!(self.line, "{} {}", computer.instruction_pointer, opcode.mnemonic()).unwrap();
+    for read in opcode.operands().0 {
+      match computer.lookup(&read) {
+        Ok(value) => write!(self.line, " {}", value).unwrap(),
+        // The instruction is about to fail on this read anyway
+        Err(_) => self.line.push_str(" ?"),
+      }
+    }
+  }
+
+  fn write<V: Word>(&mut self, address: Position, _old: &V, new: &V) {
+    write!(self.line, " [{}]={}", address, new).unwrap();
+  }
+
+  fn executed<V: Word>(&mut self, _state: &RunState<V>) {
+    if self.error.is_none() {
+      self.error = writeln!(self.out, "{}", self.line).err();
+    }
+  }
+}
+
+/// Counts what a run spends its time on.
+#[derive(Debug, Default)]
+pub struct Profiler {
+  pub steps: usize,
+  pub per_opcode: BTreeMap<&'static str, usize>,
+  pub per_address: HashMap<Position, usize>,
+  /// Every address that's been part of an executed instruction.
+  code: BTreeSet<Position>,
+  written: BTreeSet<Position>,
+}
+
+impl Profiler {
+  /// The `count` most executed instruction addresses, most executed first.
+  pub fn hottest(&self, count: usize) -> Vec<(Position, usize)> {
+    let mut addresses: Vec<_> = self.per_address.iter().map(|(&a, &n)| (a, n)).collect();
+    addresses.sort_by_key(|&(address, n)| (std::cmp::Reverse(n), address));
+    addresses.truncate(count);
+    addresses
+  }
+
+  /// Addresses that were both executed as code and written to, in either order.
+  pub fn self_modified(&self) -> Vec<Position> {
+    self.code.intersection(&self.written).copied().collect()
+  }
+
+  pub fn summary(&self) -> String {
+    let mut summary = format!("{} steps\n", self.steps);
+    for (mnemonic, n) in &self.per_opcode {
+      writeln!(summary, "  {:<20} {}", mnemonic, n).unwrap();
+    }
+    writeln!(summary, "hottest addresses:").unwrap();
+    for (address, n) in self.hottest(10) {
+      writeln!(summary, "  {:>6} {}", address, n).unwrap();
+    }
+    write!(summary, "self-modified addresses: {:?}", self.self_modified()).unwrap();
+    summary
+  }
+}
+
+impl Tracer for Profiler {
+  fn instruction<W: Word, I, O>(&mut self, computer: &Computer<W, I, O>, opcode: &Opcode<W>) {
+    let ip = computer.instruction_pointer;
+    self.steps += 1;
+    *self.per_opcode.entry(opcode.mnemonic()).or_default() += 1;
+    *self.per_address.entry(ip).or_default() += 1;
+    self.code.extend(ip..ip + opcode.size());
+  }
+
+  fn write<W: Word>(&mut self, address: Position, _old: &W, _new: &W) {
+    self.written.insert(address);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::assembler::assemble;
+
+  #[test]
+  fn writes_trace() {
+    let mut computer: Computer = vec![1101, 3, 4, 7, 4, 7, 99].into();
+    let mut tracer = TraceWriter::new(vec![]);
+    computer.run_until_blocked_with(&mut tracer).unwrap();
+    let trace = String::from_utf8(tracer.finish().unwrap()).unwrap();
+    assert_eq!("0 ADD 3 4 [7]=7\n4 OUTPUT 7\n6 HALT\n", trace);
+  }
+
+  #[test]
+  fn profiles() {
+    // Keeps its loop counter in the first operand of the LESSTHAN
+    let mut computer: Computer = assemble("
+      loop:  ADD [check+1], #1 -> [check+1]
+             OUTPUT [check+1]
+      check: LESSTHAN #-3, #0 -> [flag]
+             JUMPIFTRUE [flag], #loop
+             HALT
+      flag:  DATA 0
+    ").unwrap().into();
+    let mut profiler = Profiler::default();
+    computer.run_until_blocked_with(&mut profiler).unwrap();
+    assert_eq!(vec![-2, -1, 0], computer.output.iter().copied().collect::<Vec<_>>());
+    assert_eq!(13, profiler.steps);
+    assert_eq!(Some(&3), profiler.per_opcode.get("ADD"));
+    assert_eq!(Some(&1), profiler.per_opcode.get("HALT"));
+    assert_eq!(vec![(0, 3), (4, 3), (6, 3)], profiler.hottest(3));
+    assert_eq!(vec![7], profiler.self_modified());
+  }
+
+  #[test]
+  fn tracing_doesnt_change_results() {
+    let program = vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+    let mut plain: Computer = program.clone().into();
+    plain.run_until_blocked().unwrap();
+    let mut traced: Computer = program.into();
+    traced.run_until_blocked_with(&mut Profiler::default()).unwrap();
+    assert_eq!(plain.output, traced.output);
+    assert_eq!(plain.memory, traced.memory);
+  }
+}
@@ -0,0 +1,56 @@
+use std::convert::TryFrom;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::str::FromStr;
+
+use num::{BigInt, ToPrimitive};
+
+/// What a memory cell holds. Fixed width words report overflow instead of
+/// wrapping or panicking, and `BigInt` words never overflow at all.
+pub trait Word: Clone + Debug + Display + Default + Eq + Ord + Hash + FromStr + Send + Sync + 'static {
+  fn from_i64(value: i64) -> Self;
+  /// The value, if it fits in an `i64`.
+  fn to_i64(&self) -> Option<i64>;
+  fn checked_add(&self, other: &Self) -> Option<Self>;
+  fn checked_mul(&self, other: &Self) -> Option<Self>;
+
+  fn is_zero(&self) -> bool {
+    *self == Self::default()
+  }
+}
+
+macro_rules! fixed_width {
+  ($($word:ty),*) => {
+    $(impl Word for $word {
+      fn from_i64(value: i64) -> Self {
+        value as $word
+      }
+      fn to_i64(&self) -> Option<i64> {
+        i64::try_from(*self).ok()
+      }
+      fn checked_add(&self, other: &Self) -> Option<Self> {
+        <$word>::checked_add(*self, *other)
+      }
+      fn checked_mul(&self, other: &Self) -> Option<Self> {
+        <$word>::checked_mul(*self, *other)
+      }
+    })*
+  }
+}
+
+fixed_width!(isize, i64, i128);
+
+impl Word for BigInt {
+  fn from_i64(value: i64) -> Self {
+    BigInt::from(value)
+  }
+  fn to_i64(&self) -> Option<i64> {
+    ToPrimitive::to_i64(self)
+  }
+  fn checked_add(&self, other: &Self) -> Option<Self> {
+    Some(self + other)
+  }
+  fn checked_mul(&self, other: &Self) -> Option<Self> {
+    Some(self * other)
+  }
+}
@@ -0,0 +1,3 @@
+pub mod intcode;
+pub mod parsers;
+pub mod solutions;
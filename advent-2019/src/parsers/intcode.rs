@@ -1,10 +1,30 @@
 use anyhow::*;
+use std::fmt::Debug;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use crate::intcode::Computer;
+use crate::intcode::{Computer, Word};
 use advent_shared::parsers::vec_of_commas;
 
+/// Finds a program either by path or by name under `advent-2019/input`, so
+/// that tools can take `day5` as well as `advent-2019/input/day5.txt`.
+pub fn locate(name: &str) -> PathBuf {
+  let path = PathBuf::from(name);
+  if path.exists() {
+    return path;
+  }
+  let file = if name.ends_with(".txt") { name.to_string() } else { format!("{}.txt", name) };
+  ["advent-2019", "input", file.as_str()].iter().collect()
+}
+
 pub fn parse(file: PathBuf) -> Result<Computer> {
-  let memory = vec_of_commas(file)?;
+  parse_as(file)
+}
+
+/// Loads a program with some other word type, e.g. `i128` or `BigInt` for
+/// programs whose values don't fit in a `Value`.
+pub fn parse_as<W: Word>(file: PathBuf) -> Result<Computer<W>>
+where <W as FromStr>::Err: Debug {
+  let memory = vec_of_commas::<W>(file)?;
   Ok(memory.into())
-}
\ No newline at end of file
+}
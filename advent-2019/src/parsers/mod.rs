@@ -1,2 +1,4 @@
 mod intcode;
-pub use intcode::parse as intcode;
\ No newline at end of file
+mod range;
+pub use intcode::{locate as locate_program, parse as intcode, parse_as as intcode_as};
+pub use range::parse as range;
\ No newline at end of file
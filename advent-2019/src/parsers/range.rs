@@ -0,0 +1,8 @@
+use anyhow::*;
+use std::path::PathBuf;
+
+use advent_shared::parsers::{tuple_with, Delimiter};
+
+pub fn parse(file: PathBuf) -> Result<(u32, u32)> {
+  tuple_with(file, Delimiter::Str("-"))
+}
\ No newline at end of file
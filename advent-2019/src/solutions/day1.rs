@@ -2,21 +2,17 @@ use anyhow::*;
 
 pub mod part1 {
   use super::*;
-  pub fn solve(masses: Vec<u64>) -> Result<u64> {
-    Ok(masses.iter().map(|&m| get_fuel(m) as u64).sum())
+  pub fn solve(masses: impl Iterator<Item = Result<u64>>) -> Result<u64> {
+    masses.map(|m| m.map(|m| get_fuel(m) as u64)).sum()
   }
 }
 pub mod part2 {
   use super::*;
-  pub fn solve(masses: Vec<u64>) -> Result<u64> {
-    Ok(get_total_fuel(&masses[..]))
+  pub fn solve(masses: impl Iterator<Item = Result<u64>>) -> Result<u64> {
+    masses.map(|m| m.map(get_convergent_fuel)).sum()
   }
 }
 
-pub fn get_total_fuel(masses: &[u64]) -> u64 {
-  masses.iter().map(|&m| get_convergent_fuel(m)).sum()
-}
-
 pub fn get_convergent_fuel(module_mass: u64) -> u64 {
   let mut total = 0u64;
   let mut fuel_mass: i32 = get_fuel(module_mass) as i32;
@@ -53,11 +49,11 @@ mod tests {
 
   #[test]
   pub fn get_additional_fuel_success() {
-    assert_matches!(part1::solve(vec![12, 14, 1969, 100756]), Ok(34241));
+    assert_matches!(part1::solve(vec![12, 14, 1969, 100756].into_iter().map(Ok)), Ok(34241));
   }
 
   #[test]
   pub fn get_total_fuel_success() {
-    assert_eq!(51316, get_total_fuel(&[12, 14, 1969, 100756]));
+    assert_matches!(part2::solve(vec![12, 14, 1969, 100756].into_iter().map(Ok)), Ok(51316));
   }
 }
\ No newline at end of file
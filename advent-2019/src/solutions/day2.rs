@@ -1,5 +1,5 @@
 use anyhow::*;
-use crate::intcode::Computer;
+use crate::intcode::{cached::CachedComputer, Computer};
 
 pub mod part1 {
   use super::*;
@@ -7,25 +7,55 @@ pub mod part1 {
     let mut computer = computer;
     computer.memory[1] = 12;
     computer.memory[2] = 2;
-    computer.run()?;
+    computer.run_until_blocked()?;
     Ok(computer.memory[0])
   }
 }
 
 pub mod part2 {
   use super::*;
+  use crate::intcode::symbolic::SymbolicComputer;
+
+  const TARGET: isize = 19690720;
+
   pub fn solve(computer: Computer) -> Result<isize> {
+    match solve_symbolically(&computer) {
+      Some(answer) => Ok(answer),
+      None => brute_force(&computer),
+    }
+  }
+
+  /// Tries every noun and verb in turn.
+  pub fn brute_force(computer: &Computer) -> Result<isize> {
+    // Every attempt starts from the same decoded program, so decode it once
+    let template = CachedComputer::new(computer.clone());
     for noun in 0..100 {
       for verb in 0..100 {
-        let mut computer = computer.clone();
-        computer.memory[1] = noun;
-        computer.memory[2] = verb;
-        computer.run()?;
-        if computer.memory[0] == 19690720 {
+        let mut computer = template.clone();
+        computer.write(1, noun);
+        computer.write(2, verb);
+        computer.run_until_blocked()?;
+        if computer.computer().memory[0] == TARGET {
           return Ok(100 * noun + verb);
         }
       }
     }
     bail!("No noun/verb produces the desired constant");
   }
-}
\ No newline at end of file
+
+  /// Works out `memory[0]` as a formula in the noun and verb and solves it,
+  /// which only works if it's linear and nothing branches on them.
+  pub fn solve_symbolically(computer: &Computer) -> Option<isize> {
+    let mut symbolic = SymbolicComputer::new(computer.memory.image()).symbolic(1).symbolic(2);
+    symbolic.run().ok()?;
+    let solution = symbolic.memory(0).linear()?.solve(TARGET, &[(1, 0..100), (2, 0..100)])?;
+    let (noun, verb) = (solution[&1], solution[&2]);
+
+    // Double check it the slow way
+    let mut check = computer.clone();
+    check.memory[1] = noun;
+    check.memory[2] = verb;
+    check.run_until_blocked().ok()?;
+    Some(100 * noun + verb).filter(|_| check.memory[0] == TARGET)
+  }
+}
@@ -1,23 +1,27 @@
+use anyhow::*;
 use crate::intcode::*;
 
+/// Runs the diagnostic program for the given system ID, returning the
+/// diagnostic code it outputs last.
+fn diagnose(mut computer: Computer, system: isize) -> Result<isize> {
+    computer.input.push_back(system);
+    match computer.run_until_blocked()? {
+        RunState::Halted => {},
+        state => bail!("Diagnostic program stopped ({:?}) without halting", state),
+    }
+    computer.output.pop_back().ok_or_else(|| anyhow!("Diagnostic program halted without any output"))
+}
+
 pub mod part1 {
     use super::*;
-    use anyhow::*;
     pub fn solve(computer: Computer) -> Result<isize> {
-        let mut computer = computer;
-        computer.input.push_back(1);
-        computer.run().unwrap();
-        Ok(computer.output.pop_back().unwrap())
+        diagnose(computer, 1)
     }
 }
 
 pub mod part2 {
     use super::*;
-    use anyhow::*;
     pub fn solve(computer: Computer) -> Result<isize> {
-        let mut computer = computer;
-        computer.input.push_back(5);
-        computer.run().unwrap();
-        Ok(computer.output.pop_back().unwrap())
+        diagnose(computer, 5)
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use advent_shared::AdventParse;
 use anyhow::*;
 
 pub mod part1 {
@@ -23,14 +23,21 @@ pub mod part2 {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, AdventParse)]
 pub enum Instruction {
+    #[pattern("N{}")]
     North(i32),
+    #[pattern("S{}")]
     South(i32),
+    #[pattern("E{}")]
     East(i32),
+    #[pattern("W{}")]
     West(i32),
+    #[pattern("L{}")]
     Left(i32),
+    #[pattern("R{}")]
     Right(i32),
+    #[pattern("F{}")]
     Forward(i32),
 }
 
@@ -128,21 +135,3 @@ impl BoatWithWaypoint {
     }
 }
 
-impl FromStr for Instruction {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (dir, dist) = (&s[0..1], s[1..].parse::<i32>().unwrap());
-        Ok(match dir {
-            "N" => Instruction::North(dist),
-            "S" => Instruction::South(dist),
-            "E" => Instruction::East(dist),
-            "W" => Instruction::West(dist),
-            "L" => Instruction::Left(dist),
-            "R" => Instruction::Right(dist),
-            "F" => Instruction::Forward(dist),
-            _ => panic!()
-        })
-    }
-}
-
@@ -1,4 +1,5 @@
 use std::{collections::{HashMap, HashSet}, iter::FromIterator, str::FromStr};
+use advent_shared::{ParseError, parsers::{extract_ints, IntOptions}};
 use anyhow::*;
 
 pub mod part1 {
@@ -48,19 +49,16 @@ pub struct TicketRule {
   ranges: Vec<(u32, u32)>,
 }
 impl FromStr for TicketRule {
-  type Err = ();
+  type Err = ParseError;
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let mut split = s.split(":");
-    let (field, ranges) = (split.next().unwrap(), split.next().unwrap());
-    let mut parsed_ranges = vec![];
-    for range in ranges.split("or") {
-      let mut split = range.split("-");
-      let (min, max) = (split.next().unwrap().trim(), split.next().unwrap().trim());
-      let (min, max) = (min.parse::<u32>().unwrap(), max.parse::<u32>().unwrap());
-      parsed_ranges.push((min, max));
+    let (field, ranges) = s.split_once(':').ok_or_else(|| ParseError::at(s, s.len(), "expected \":\""))?;
+    let bounds = extract_ints::<u32>(ranges, IntOptions::ranges()).map_err(|e| e.offset(field.chars().count() + 1))?;
+    if bounds.len() % 2 != 0 {
+      return Err(ParseError::at(s, s.len(), "expected min-max pairs"));
     }
-    return Ok(TicketRule { field: field.to_string(), ranges: parsed_ranges });
+    let ranges = bounds.chunks(2).map(|r| (r[0], r[1])).collect();
+    Ok(TicketRule { field: field.to_string(), ranges })
   }
 }
 
@@ -97,10 +95,10 @@ impl FromStr for TicketData {
           rules.push(line.parse::<TicketRule>().unwrap());
         },
         ParsePhase::Mine => {
-          my_ticket = line.split(",").map(|s| s.parse::<u32>().unwrap()).collect();
+          my_ticket = extract_ints(line, IntOptions::default()).unwrap();
         },
         ParsePhase::Nearby => {
-          nearby_tickets.push(line.split(",").map(|s| s.parse::<u32>().unwrap()).collect());
+          nearby_tickets.push(extract_ints(line, IntOptions::default()).unwrap());
         }
       }
     }
@@ -175,4 +173,15 @@ pub fn get_field_to_index_map(data: &TicketData) -> HashMap<usize, usize> {
   }
 
   return field_to_index_map;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn locates_rule_errors() {
+    let rule = "class: 1-3 or 5-7".parse::<TicketRule>().unwrap();
+    assert_eq!(vec![(1, 3), (5, 7)], rule.ranges);
+    // Columns count characters, not bytes
+    assert_eq!(11, "classé: 1-99999999999".parse::<TicketRule>().map(|_| ()).unwrap_err().column);
+  }
+}
@@ -1,7 +1,8 @@
+use advent_shared::AdventParse;
 use anyhow::*;
-use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Debug, AdventParse)]
+#[pattern("{min}-{max} {char}: {password}")]
 pub struct PasswordRecord {
     min: usize,
     max: usize,
@@ -9,34 +10,6 @@ pub struct PasswordRecord {
     password: String,
 }
 
-impl FromStr for PasswordRecord {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split(" ");
-        let (range, char, password) = (
-            parts.next().ok_or(())?,
-            parts.next().ok_or(())?,
-            parts.next().ok_or(())?,
-        );
-
-        let mut parts = range.split('-');
-        let (min, max) = (parts.next().ok_or(())?, parts.next().ok_or(())?);
-        let (min, max) = (min.parse().or(Err(()))?, max.parse().or(Err(()))?);
-
-        let char = char.chars().next().ok_or(())?;
-
-        let password = password.to_string();
-
-        Ok(PasswordRecord {
-            min,
-            max,
-            char,
-            password,
-        })
-    }
-}
-
 pub fn count_valid<F>(inputs: &[PasswordRecord], v: F) -> Result<u64>
 where
     F: Fn(&PasswordRecord) -> bool,
@@ -114,6 +87,12 @@ mod test {
         );
     }
 
+    #[test]
+    fn parse_failure() {
+        assert_eq!(12, "1-3 a abcde".parse::<PasswordRecord>().unwrap_err().column);
+        assert_eq!(3, "1-x a: abcde".parse::<PasswordRecord>().unwrap_err().column);
+    }
+
     mod part1 {
         use super::super::part1::*;
         use super::super::*;
@@ -1,6 +1,7 @@
 use std::{collections::{HashMap, HashSet}, str::FromStr};
 use std::{collections::{VecDeque}};
 use std::iter::FromIterator;
+use advent_shared::{ParseError, parsers::{extract_ints, IntOptions}};
 
 pub mod part1 {
   use super::*;
@@ -30,15 +31,12 @@ pub struct RecursiveCombat(Deck, Deck);
 pub enum Player { Player1, Player2 }
 
 impl FromStr for Deck {
-  type Err = ();
+  type Err = ParseError;
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    Ok(Deck(
-      VecDeque::from_iter(s.lines()
-       .map(|s| s.parse::<usize>())
-       .filter(|s| s.is_ok())
-       .map(|s| s.unwrap()))
-    ))
+    // The first line is the "Player N:" header, whose number isn't a card.
+    let cards = s.split_once('\n').map_or("", |(_, cards)| cards);
+    Ok(Deck(VecDeque::from_iter(extract_ints(cards, IntOptions::default())?)))
   }
 }
 
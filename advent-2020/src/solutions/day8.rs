@@ -1,5 +1,6 @@
-use std::{str::FromStr, collections::HashSet};
+use std::collections::HashSet;
 
+use advent_shared::AdventParse;
 use anyhow::*;
 
 pub mod part1 {
@@ -31,28 +32,16 @@ pub mod part2 {
   }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, AdventParse)]
 pub enum Opcode {
+  #[pattern("acc {}")]
   Acc(i32),
+  #[pattern("jmp {}")]
   Jump(i32),
+  #[pattern("nop {}")]
   Nop(i32),
 }
 
-impl FromStr for Opcode {
-  type Err = ();
-
-  fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let mut split = s.split(' ');
-    let (op, count) = (split.next().unwrap(), split.next().unwrap().parse::<i32>().unwrap());
-    Ok(match op {
-      "acc" => Opcode::Acc(count),
-      "jmp" => Opcode::Jump(count),
-      "nop" => Opcode::Nop(count),
-      _ => panic!()
-    })
-  }
-}
-
 struct Machine {
   instructions: Vec<Opcode>,
   instruction_pointer: usize,
@@ -111,6 +100,15 @@ mod tests {
     assert_matches!("jmp -5".parse::<Opcode>(), Ok(Opcode::Jump(-5)));
   }
 
+  #[test]
+  fn parse_failure() {
+    let err = "acc +x".parse::<Opcode>().unwrap_err();
+    assert_eq!(5, err.column);
+    let err = "mul 3".parse::<Opcode>().unwrap_err();
+    assert_eq!(1, err.column);
+    assert_eq!("column 1: expected one of \"acc {}\", \"jmp {}\", \"nop {}\"", err.to_string());
+  }
+
   #[test]
   fn step_test() {
     let mut machine = Machine::new(vec![Opcode::Acc(20)]);
@@ -1,5 +1,4 @@
-use std::str::FromStr;
-
+use advent_shared::AdventParse;
 use anyhow::*;
 
 pub mod part1 {
@@ -38,30 +37,16 @@ pub mod part2 {
   }
 }
 
+#[derive(AdventParse)]
 pub enum Command {
+  #[pattern("forward {}")]
   Forward(u64),
+  #[pattern("down {}")]
   Down(u64),
+  #[pattern("up {}")]
   Up(u64)
 }
 
-impl FromStr for Command {
-  type Err = String;
-
-  fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let parts: Vec<_> = s.split(" ").collect();
-    if parts.len() != 2 {
-      return Err("Too many parts".into())
-    }
-    let dist = parts[1].parse().unwrap();
-    match parts[0] {
-      "forward" => Ok(Command::Forward(dist)),
-      "down" => Ok(Command::Down(dist)),
-      "up" => Ok(Command::Up(dist)),
-      _ => Err("Unrecognized Command".into())
-    }
-  }
-}
-
 #[cfg(test)]
 pub mod test {
   use super::*;
@@ -1,27 +1,14 @@
-use std::str::FromStr;
-
+use advent_shared::AdventParse;
 use anyhow::*;
 
-#[derive(Debug)]
+#[derive(Debug, AdventParse)]
 pub enum Dial {
+    #[pattern("L{}")]
     Left(u64),
+    #[pattern("R{}")]
     Right(u64),
 }
 
-impl FromStr for Dial {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        Ok(
-            match s.chars().nth(0).context("can't parse nonempty strings")? {
-                'L' => Dial::Left(s[1..].parse()?),
-                'R' => Dial::Right(s[1..].parse()?),
-                _ => bail!("invalid entry {}", s),
-            },
-        )
-    }
-}
-
 pub mod part1 {
     use super::*;
     pub fn solve(input: Vec<Dial>) -> Result<u64> {
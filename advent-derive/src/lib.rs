@@ -0,0 +1,224 @@
+//! `#[derive(AdventParse)]`, which generates a `FromStr` impl from a
+//! `#[pattern("...")]` template; see `advent_shared::pattern` for the template
+//! syntax and matching rules.
+//!
+//! ```ignore
+//! #[derive(AdventParse)]
+//! #[pattern("{min}-{max} {letter}: {password}")]
+//! struct PasswordRecord { min: usize, max: usize, letter: char, password: String }
+//!
+//! #[derive(AdventParse)]
+//! enum Opcode {
+//!     #[pattern("acc {}")] Acc(i32),
+//!     #[pattern("nop")] Nop,
+//! }
+//! ```
+//!
+//! Structs with named fields use `{name}` fields, tuple structs and variants use
+//! `{}` fields in order. Enum variants are tried top to bottom, and if none match
+//! the error from the one that got furthest into the line is reported.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Fields, LitStr, parse_macro_input, spanned::Spanned};
+
+#[proc_macro_derive(AdventParse, attributes(pattern))]
+pub fn derive_advent_parse(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let template = pattern_attr(&input.attrs, input.span())?;
+            construct(&template, quote!(#name), &data.fields)?
+        }
+        Data::Enum(data) => {
+            if data.variants.is_empty() {
+                return Err(syn::Error::new(input.span(), "AdventParse needs at least one variant"));
+            }
+            let mut attempts = vec![];
+            let mut templates = vec![];
+            for variant in &data.variants {
+                let template = pattern_attr(&variant.attrs, variant.span())?;
+                let ident = &variant.ident;
+                attempts.push(construct(&template, quote!(#name::#ident), &variant.fields)?);
+                templates.push(format!("{:?}", template.value()));
+            }
+            let expected = format!("expected one of {}", templates.join(", "));
+            quote! {
+                let mut __error: ::core::option::Option<::advent_shared::ParseError> = ::core::option::Option::None;
+                #(
+                    match #attempts {
+                        ::core::result::Result::Ok(value) => return ::core::result::Result::Ok(value),
+                        ::core::result::Result::Err(e) => {
+                            __error = ::core::option::Option::Some(::advent_shared::pattern::furthest(__error, e));
+                        }
+                    }
+                )*
+                match __error {
+                    ::core::option::Option::Some(e) if e.column > 1 => ::core::result::Result::Err(e),
+                    _ => ::core::result::Result::Err(::advent_shared::ParseError::new(1, #expected)),
+                }
+            }
+        }
+        Data::Union(_) => return Err(syn::Error::new(input.span(), "AdventParse can't be derived for unions")),
+    };
+    Ok(quote! {
+        impl #impl_generics ::core::str::FromStr for #name #ty_generics #where_clause {
+            type Err = ::advent_shared::ParseError;
+
+            fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                #body
+            }
+        }
+    })
+}
+
+fn pattern_attr(attrs: &[Attribute], span: proc_macro2::Span) -> syn::Result<LitStr> {
+    let mut found = attrs.iter().filter(|a| a.path().is_ident("pattern"));
+    match (found.next(), found.next()) {
+        (Some(attr), None) => attr.parse_args::<LitStr>(),
+        (Some(_), Some(extra)) => Err(syn::Error::new(extra.span(), "only one #[pattern(...)] is allowed")),
+        (None, _) => Err(syn::Error::new(span, "missing #[pattern(\"...\")] attribute")),
+    }
+}
+
+/// Builds an expression of type `Result<Self, ParseError>` that matches `s`
+/// against `template` and constructs `path` from the captured fields.
+fn construct(template: &LitStr, path: TokenStream2, fields: &Fields) -> syn::Result<TokenStream2> {
+    let segments = split_template(template)?;
+    let field_names: Vec<Option<String>> = segments
+        .iter()
+        .filter_map(|s| match s {
+            Segment::Field(name) => Some(name.clone()),
+            Segment::Literal(_) => None,
+        })
+        .collect();
+    let segment_tokens = segments.iter().map(|s| match s {
+        Segment::Literal(text) => {
+            quote!(::advent_shared::pattern::Segment::Literal(::std::borrow::Cow::Borrowed(#text)))
+        }
+        Segment::Field(None) => quote!(::advent_shared::pattern::Segment::Field(::core::option::Option::None)),
+        Segment::Field(Some(name)) => {
+            quote!(::advent_shared::pattern::Segment::Field(::core::option::Option::Some(#name)))
+        }
+    });
+
+    let value = match fields {
+        Fields::Unit => {
+            if !field_names.is_empty() {
+                return Err(syn::Error::new(template.span(), "pattern has fields but the type has none"));
+            }
+            quote!(#path)
+        }
+        Fields::Unnamed(unnamed) => {
+            if let Some(Some(name)) = field_names.iter().find(|n| n.is_some()) {
+                return Err(syn::Error::new(
+                    template.span(),
+                    format!("tuple fields are positional, use {{}} instead of {{{}}}", name),
+                ));
+            }
+            if field_names.len() != unnamed.unnamed.len() {
+                return Err(syn::Error::new(
+                    template.span(),
+                    format!("pattern has {} fields but the type has {}", field_names.len(), unnamed.unnamed.len()),
+                ));
+            }
+            let indices = 0..field_names.len();
+            quote!(#path(#(__captures.parse(#indices)?),*))
+        }
+        Fields::Named(named) => {
+            let mut inits = vec![];
+            for field in &named.named {
+                let ident = field.ident.as_ref().unwrap();
+                let wanted = ident.to_string();
+                let mut positions = field_names
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, n)| n.as_deref() == Some(wanted.as_str()))
+                    .map(|(i, _)| i);
+                let index = match (positions.next(), positions.next()) {
+                    (Some(index), None) => index,
+                    (Some(_), Some(_)) => {
+                        return Err(syn::Error::new(template.span(), format!("field {{{}}} appears twice", wanted)));
+                    }
+                    (None, _) => {
+                        return Err(syn::Error::new(template.span(), format!("pattern is missing field {{{}}}", wanted)));
+                    }
+                };
+                inits.push(quote!(#ident: __captures.parse(#index)?));
+            }
+            if let Some(name) = field_names.iter().find(|n| match n {
+                Some(n) => !named.named.iter().any(|f| f.ident.as_ref().unwrap() == n),
+                None => true,
+            }) {
+                let shown = name.as_deref().unwrap_or("");
+                return Err(syn::Error::new(template.span(), format!("no field named {{{}}}", shown)));
+            }
+            quote!(#path { #(#inits),* })
+        }
+    };
+
+    Ok(quote! {
+        (|| -> ::core::result::Result<Self, ::advent_shared::ParseError> {
+            const SEGMENTS: &[::advent_shared::pattern::Segment<'static>] = &[#(#segment_tokens),*];
+            #[allow(unused_variables)]
+            let __captures = ::advent_shared::pattern::captures(SEGMENTS, s)?;
+            ::core::result::Result::Ok(#value)
+        })()
+    })
+}
+
+enum Segment {
+    Literal(String),
+    Field(Option<String>),
+}
+
+/// Mirrors `advent_shared::pattern::Pattern::new`, so that template mistakes are
+/// reported at compile time instead of on the first parse.
+fn split_template(template: &LitStr) -> syn::Result<Vec<Segment>> {
+    let text = template.value();
+    let error = |message: &str| syn::Error::new(template.span(), format!("invalid pattern {:?}: {}", text, message));
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                literal.push(c);
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_alphanumeric() || c == '_' => name.push(c),
+                        Some(c) => return Err(error(&format!("invalid character {:?} in field name", c))),
+                        None => return Err(error("unclosed '{'")),
+                    }
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                } else if let Some(Segment::Field(_)) = segments.last() {
+                    return Err(error("fields must be separated by literal text"));
+                }
+                segments.push(Segment::Field(if name.is_empty() { None } else { Some(name) }));
+            }
+            '}' => return Err(error("unmatched '}'")),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+
+/// An error encountered while parsing puzzle input, pointing at where in the
+/// input it happened. Columns are 1-based and counted in characters; the line is
+/// only known once a caller that splits the file into lines attaches it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: Option<usize>,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(column: usize, message: impl Into<String>) -> Self {
+        ParseError { line: None, column, message: message.into() }
+    }
+
+    /// Builds an error for the byte `offset` into `input`.
+    pub fn at(input: &str, offset: usize, message: impl Into<String>) -> Self {
+        let column = input[..offset.min(input.len())].chars().count() + 1;
+        ParseError::new(column, message)
+    }
+
+    pub fn on_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    /// Shifts the error right by `columns`, for when the parsed text was itself
+    /// a slice of a larger line.
+    pub fn offset(mut self, columns: usize) -> Self {
+        self.column += columns;
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}, column {}: {}", line, self.column, self.message),
+            None => write!(f, "column {}: {}", self.column, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,112 @@
+//! Reading puzzle input files into a consistent shape before any parser sees
+//! them, so that a CRLF checkout, a BOM from some editor, or a trailing newline
+//! doesn't turn into empty or `"\r"`-suffixed records.
+
+use anyhow::*;
+use std::{fs, path::Path};
+
+const BOM: char = '\u{feff}';
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trailing {
+    Keep,
+    /// Drop any newlines at the end of the file.
+    TrimNewlines,
+    /// Drop any whitespace at the end of the file, including trailing spaces
+    /// on the last line.
+    TrimWhitespace,
+}
+
+impl Trailing {
+    /// What's left of the last line of a file once this has trimmed its end.
+    pub fn trim(self, line: &str) -> &str {
+        match self {
+            Trailing::Keep => line,
+            Trailing::TrimNewlines => line.trim_end_matches(['\n', '\r']),
+            Trailing::TrimWhitespace => line.trim_end(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Normalize {
+    pub line_endings: bool,
+    pub bom: bool,
+    pub trailing: Trailing,
+}
+
+impl Default for Normalize {
+    fn default() -> Self {
+        Normalize { line_endings: true, bom: true, trailing: Trailing::TrimNewlines }
+    }
+}
+
+impl Normalize {
+    /// Leaves the input exactly as it is on disk, for puzzles where every
+    /// byte of whitespace matters.
+    pub const NONE: Normalize = Normalize { line_endings: false, bom: false, trailing: Trailing::Keep };
+
+    pub fn apply(&self, contents: String) -> String {
+        let mut contents = contents;
+        if self.bom && contents.starts_with(BOM) {
+            contents.drain(..BOM.len_utf8());
+        }
+        if self.line_endings && contents.contains('\r') {
+            contents = contents.replace("\r\n", "\n");
+        }
+        let len = self.trailing.trim(&contents).len();
+        contents.truncate(len);
+        contents
+    }
+
+    /// Normalizes a single line read on its own, where only the BOM (on the
+    /// first line) and the line ending apply. Trailing lines are up to the
+    /// caller, which has to hold back anything `trailing` might trim until it
+    /// knows whether the file ends there.
+    pub fn apply_line<'a>(&self, line: &'a str, first: bool) -> &'a str {
+        let line = if self.bom && first { line.strip_prefix(BOM).unwrap_or(line) } else { line };
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        if self.line_endings { line.strip_suffix('\r').unwrap_or(line) } else { line }
+    }
+}
+
+/// Reads `file` with the default normalization, which every shared parser uses
+/// unless it's given another.
+pub fn read_input(file: &Path) -> Result<String> {
+    read_input_with(file, Normalize::default())
+}
+
+pub fn read_input_with(file: &Path, normalize: Normalize) -> Result<String> {
+    Ok(normalize.apply(fs::read_to_string(file)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_normalization() {
+        let normalize = Normalize::default();
+        assert_eq!("1,2,3", normalize.apply("\u{feff}1,2,3\r\n".to_string()));
+        assert_eq!("a\nb\n\nc", normalize.apply("a\r\nb\r\n\r\nc\r\n\r\n".to_string()));
+        assert_eq!("  x ", normalize.apply("  x \n".to_string()));
+    }
+
+    #[test]
+    fn configurable() {
+        let contents = "\u{feff} x \r\n".to_string();
+        assert_eq!(contents, Normalize::NONE.apply(contents.clone()));
+        let trim = Normalize { trailing: Trailing::TrimWhitespace, ..Normalize::default() };
+        assert_eq!(" x", trim.apply(contents.clone()));
+        let keep = Normalize { trailing: Trailing::Keep, ..Normalize::default() };
+        assert_eq!(" x \n", keep.apply(contents));
+    }
+
+    #[test]
+    fn single_lines() {
+        let normalize = Normalize::default();
+        assert_eq!("12", normalize.apply_line("\u{feff}12\r\n", true));
+        assert_eq!("\u{feff}12", normalize.apply_line("\u{feff}12\r\n", false));
+        assert_eq!("12\r", Normalize::NONE.apply_line("12\r\n", false));
+    }
+}
@@ -1,6 +1,12 @@
 use anyhow::*;
-use std::path::PathBuf;
+use parsers::{LinesIter, MappedLines};
+use std::{fmt::Display, fs::File, io::BufReader, path::PathBuf, str::FromStr};
+pub mod input;
 pub mod parsers;
+pub mod pattern;
+mod error;
+pub use error::ParseError;
+pub use advent_derive::AdventParse;
 
 pub fn run<P, I, S, R>(year: u32, file: &str, p: P, s: S) -> Result<R>
     where
@@ -9,6 +15,25 @@ pub fn run<P, I, S, R>(year: u32, file: &str, p: P, s: S) -> Result<R>
     s(p([format!(r"advent-{}", year).as_str(), "input", file].iter().collect())?)
 }
 
+/// Like `run`, but hands the solver an iterator that parses the input a line
+/// at a time, for solvers that never need the whole input at once.
+pub fn run_lines<T, S, R>(year: u32, file: &str, s: S) -> Result<R>
+    where
+        T : FromStr,
+        <T as FromStr>::Err : Display,
+        S : Fn(LinesIter<BufReader<File>, T>) -> Result<R> {
+    run(year, file, parsers::lines_iter, s)
+}
+
+/// Like `run_lines`, but reading the input through a memory map.
+pub fn run_mapped_lines<T, S, R>(year: u32, file: &str, s: S) -> Result<R>
+    where
+        T : FromStr,
+        <T as FromStr>::Err : Display,
+        S : Fn(MappedLines<T>) -> Result<R> {
+    run(year, file, parsers::mapped_lines_iter, s)
+}
+
 #[cfg(test)]
 mod tests {
 }
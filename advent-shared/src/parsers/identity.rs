@@ -1,11 +1,20 @@
 use anyhow::*;
-use std::{fmt::Debug, path::PathBuf, fs, str::FromStr};
+use crate::input::{read_input_with, Normalize};
+use std::{fmt::Debug, path::PathBuf, str::FromStr};
 
 pub fn identity<T>(file: PathBuf) -> Result<T>
 where
     T: FromStr,
     <T as FromStr>::Err: Debug,
 {
-    let contents = fs::read_to_string(file)?;
+    identity_with(file, Normalize::default())
+}
+
+pub fn identity_with<T>(file: PathBuf, normalize: Normalize) -> Result<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Debug,
+{
+    let contents = read_input_with(&file, normalize)?;
     Ok(contents.parse::<T>().unwrap())
 }
\ No newline at end of file
@@ -0,0 +1,119 @@
+use crate::{input::read_input, ParseError};
+use anyhow::Result;
+use std::{num::IntErrorKind, num::ParseIntError, path::PathBuf, str::FromStr};
+
+/// The primitive integer types that can be pulled out of free-form text.
+pub trait Integer: FromStr<Err = ParseIntError> + Copy {
+    const SIGNED: bool;
+    const NAME: &'static str;
+}
+
+macro_rules! integer {
+    ($signed:expr => $($t:ty),*) => {
+        $(impl Integer for $t {
+            const SIGNED: bool = $signed;
+            const NAME: &'static str = stringify!($t);
+        })*
+    };
+}
+integer!(true => i8, i16, i32, i64, i128, isize);
+integer!(false => u8, u16, u32, u64, u128, usize);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IntOptions {
+    /// Treat a `-` that directly follows a digit as a range separator, so that
+    /// `1-3` is `[1, 3]` rather than `[1, -3]` for signed types. Unsigned types
+    /// never read `-` as a sign.
+    pub dash_is_separator: bool,
+}
+
+impl IntOptions {
+    pub fn ranges() -> Self {
+        IntOptions { dash_is_separator: true }
+    }
+}
+
+/// Every integer in `line`, ignoring whatever text surrounds them.
+pub fn extract_ints<T: Integer>(line: &str, options: IntOptions) -> Result<Vec<T>, ParseError> {
+    let bytes = line.as_bytes();
+    let mut ints = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let negative = T::SIGNED
+            && bytes[i] == b'-'
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)
+            && !(options.dash_is_separator && i > 0 && bytes[i - 1].is_ascii_digit());
+        if !negative && !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        let text = &line[start..i];
+        let value = text.parse::<T>().map_err(|e| {
+            let message = match e.kind() {
+                IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                    format!("{} doesn't fit in {}", text, T::NAME)
+                }
+                _ => format!("invalid integer {:?}: {}", text, e),
+            };
+            ParseError::at(line, start, message)
+        })?;
+        ints.push(value);
+    }
+    Ok(ints)
+}
+
+pub fn ints<T: Integer>(file: PathBuf) -> Result<Vec<T>> {
+    ints_with(file, IntOptions::default())
+}
+
+pub fn ints_with<T: Integer>(file: PathBuf, options: IntOptions) -> Result<Vec<T>> {
+    Ok(ints_per_line_with(file, options)?.into_iter().flatten().collect())
+}
+
+pub fn ints_per_line<T: Integer>(file: PathBuf) -> Result<Vec<Vec<T>>> {
+    ints_per_line_with(file, IntOptions::default())
+}
+
+pub fn ints_per_line_with<T: Integer>(file: PathBuf, options: IntOptions) -> Result<Vec<Vec<T>>> {
+    let contents = read_input(&file)?;
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| extract_ints(line, options).map_err(|e| e.on_line(i + 1).into()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surrounding_text() {
+        assert_eq!(Ok(vec![939u64, 7, 13, 59]), extract_ints("939: 7,13,x,x,59,x", IntOptions::default()));
+        assert_eq!(Ok(vec![1, 2, 3]), extract_ints::<i32>("Player 1: [2] and 3", IntOptions::default()));
+        assert_eq!(Ok(Vec::<u8>::new()), extract_ints("no numbers here", IntOptions::default()));
+    }
+
+    #[test]
+    fn negatives_and_ranges() {
+        assert_eq!(Ok(vec![-12, 5]), extract_ints::<i64>("acc -12 +5", IntOptions::default()));
+        assert_eq!(Ok(vec![1, -3]), extract_ints::<i64>("1-3", IntOptions::default()));
+        assert_eq!(Ok(vec![1, 3]), extract_ints::<i64>("1-3", IntOptions::ranges()));
+        assert_eq!(Ok(vec![-5, -3]), extract_ints::<i64>("-5--3", IntOptions::ranges()));
+        assert_eq!(Ok(vec![246540, 787419]), extract_ints::<u32>("246540-787419", IntOptions::default()));
+    }
+
+    #[test]
+    fn overflow() {
+        let err = extract_ints::<u8>("ok 12, too big 256", IntOptions::default()).unwrap_err();
+        assert_eq!(16, err.column);
+        assert_eq!("column 16: 256 doesn't fit in u8", err.to_string());
+        assert!(extract_ints::<i8>("-128 -129", IntOptions::default()).is_err());
+        assert_eq!(Ok(vec![u128::MAX]), extract_ints("340282366920938463463374607431768211455", IntOptions::default()));
+    }
+}
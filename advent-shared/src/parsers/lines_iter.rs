@@ -0,0 +1,286 @@
+use crate::{input::{Normalize, Trailing}, ParseError};
+use anyhow::Result;
+use memmap2::Mmap;
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader},
+    marker::PhantomData,
+    ops::Range,
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// Lines that might still turn out to be at the end of the file, held back
+/// until it's known whether they are, so that streamed input is trimmed just as
+/// `Normalize::apply` trims a whole file.
+struct Held<L> {
+    lines: VecDeque<(usize, L)>,
+    /// How many of `lines` have something after them that won't be trimmed.
+    ready: usize,
+    /// Whether the first line that isn't ready has anything left on it once
+    /// it's trimmed, and so would be the file's last line.
+    last: bool,
+}
+
+impl<L> Held<L> {
+    fn new() -> Self {
+        Held { lines: VecDeque::new(), ready: 0, last: false }
+    }
+
+    /// Adds the next line, where `blank` says whether `trailing` would trim it
+    /// away entirely if nothing came after it.
+    fn push(&mut self, number: usize, line: L, trailing: Trailing, blank: bool) {
+        self.lines.push_back((number, line));
+        if trailing == Trailing::Keep {
+            self.ready = self.lines.len();
+        } else if !blank {
+            self.ready = self.lines.len() - 1;
+            self.last = true;
+        }
+    }
+
+    fn pop(&mut self) -> Option<(usize, L)> {
+        if self.ready == 0 {
+            return None;
+        }
+        self.ready -= 1;
+        self.lines.pop_front()
+    }
+
+    /// Once there's nothing more to read, the last line still to be trimmed,
+    /// if there is one. Everything after it is dropped.
+    fn finish(&mut self) -> Option<(usize, L)> {
+        let last = if self.last { self.lines.pop_front() } else { None };
+        self.lines.clear();
+        self.last = false;
+        last
+    }
+}
+
+/// Parses one line at a time out of a `BufRead`, without ever holding more
+/// than the current line in memory, besides any blank lines that might be
+/// trailing.
+pub struct LinesIter<R, T> {
+    reader: R,
+    buffer: String,
+    line: usize,
+    normalize: Normalize,
+    held: Held<String>,
+    done: bool,
+    _parsed: PhantomData<fn() -> T>,
+}
+
+pub fn lines_iter<T>(file: PathBuf) -> Result<LinesIter<BufReader<File>, T>>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    Ok(lines_from(BufReader::new(File::open(file)?)))
+}
+
+pub fn lines_from<R, T>(reader: R) -> LinesIter<R, T>
+where
+    R: BufRead,
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    LinesIter {
+        reader,
+        buffer: String::new(),
+        line: 0,
+        normalize: Normalize::default(),
+        held: Held::new(),
+        done: false,
+        _parsed: PhantomData,
+    }
+}
+
+impl<R, T> LinesIter<R, T> {
+    pub fn normalized(self, normalize: Normalize) -> Self {
+        LinesIter { normalize, ..self }
+    }
+}
+
+impl<R, T> Iterator for LinesIter<R, T>
+where
+    R: BufRead,
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let trailing = self.normalize.trailing;
+        loop {
+            if let Some((number, line)) = self.held.pop() {
+                return Some(parse_line(&line, number));
+            }
+            if self.done {
+                let (number, line) = self.held.finish()?;
+                return Some(parse_line(trailing.trim(&line), number));
+            }
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    let line = self.normalize.apply_line(&self.buffer, self.line == 1);
+                    let blank = trailing.trim(line).is_empty();
+                    self.held.push(self.line, line.to_string(), trailing, blank);
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+/// Like [`LinesIter`], but over a memory map of the whole file, letting the OS
+/// page the input in and out instead of copying each line into a buffer.
+pub struct MappedLines<T> {
+    map: Mmap,
+    position: usize,
+    line: usize,
+    normalize: Normalize,
+    /// Where in the map each held line is.
+    held: Held<Range<usize>>,
+    _parsed: PhantomData<fn() -> T>,
+}
+
+pub fn mapped_lines_iter<T>(file: PathBuf) -> Result<MappedLines<T>>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    let file = File::open(file)?;
+    // SAFETY: puzzle inputs aren't modified while we're solving them; if one is
+    // truncated underneath us we'd fault, which is no worse than a bad parse.
+    let map = unsafe { Mmap::map(&file)? };
+    Ok(MappedLines { map, position: 0, line: 0, normalize: Normalize::default(), held: Held::new(), _parsed: PhantomData })
+}
+
+impl<T> MappedLines<T> {
+    pub fn normalized(self, normalize: Normalize) -> Self {
+        MappedLines { normalize, ..self }
+    }
+
+    /// The normalized text of the line at `range`, or why it isn't text.
+    fn text(&self, range: Range<usize>, number: usize) -> Result<&str> {
+        let bytes = &self.map[range];
+        match std::str::from_utf8(bytes) {
+            Ok(line) => Ok(self.normalize.apply_line(line, number == 1)),
+            Err(e) => {
+                // Everything before the bad byte is known to be fine
+                let valid = std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default();
+                Err(ParseError::at(valid, valid.len(), e.to_string()).on_line(number).into())
+            }
+        }
+    }
+}
+
+impl<T> Iterator for MappedLines<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let trailing = self.normalize.trailing;
+        loop {
+            if let Some((number, range)) = self.held.pop() {
+                return Some(self.text(range, number).and_then(|line| parse_line(line, number)));
+            }
+            let rest = &self.map[self.position..];
+            if rest.is_empty() {
+                let (number, range) = self.held.finish()?;
+                return Some(self.text(range, number).and_then(|line| parse_line(trailing.trim(line), number)));
+            }
+            let len = rest.iter().position(|&b| b == b'\n').map_or(rest.len(), |i| i + 1);
+            let range = self.position..self.position + len;
+            self.position += len;
+            self.line += 1;
+            // A line that isn't text can't be trimmed, so it has to be reported
+            let blank = self.text(range.clone(), self.line).is_ok_and(|line| trailing.trim(line).is_empty());
+            self.held.push(self.line, range, trailing, blank);
+        }
+    }
+}
+
+fn parse_line<T>(line: &str, number: usize) -> Result<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    line.parse::<T>().map_err(|e| ParseError::new(1, format!("invalid value {:?}: {}", line, e)).on_line(number).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, io::Cursor};
+
+    #[test]
+    fn streams_lines() {
+        let lines: Vec<u32> = lines_from(Cursor::new("\u{feff}12\r\n14\n1969")).collect::<Result<_>>().unwrap();
+        assert_eq!(vec![12, 14, 1969], lines);
+    }
+
+    #[test]
+    fn opt_out_of_normalization() {
+        let lines = lines_from::<_, String>(Cursor::new("\u{feff}a\r\nb\n")).normalized(Normalize::NONE);
+        assert_eq!(vec!["\u{feff}a\r", "b"], lines.collect::<Result<Vec<_>>>().unwrap());
+    }
+
+    #[test]
+    fn reports_failing_line() {
+        let mut lines = lines_from::<_, u32>(Cursor::new("12\nfourteen\n"));
+        assert!(lines.next().unwrap().is_ok());
+        let err = lines.next().unwrap().unwrap_err();
+        assert_eq!("line 2, column 1: invalid value \"fourteen\": invalid digit found in string", err.to_string());
+        assert_eq!(Some(2), err.downcast_ref::<ParseError>().and_then(|e| e.line));
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn trims_trailing_lines() {
+        let parse = |normalize, input: &str| {
+            let lines = lines_from::<_, String>(Cursor::new(input.to_string())).normalized(normalize);
+            lines.collect::<Result<Vec<_>>>().unwrap()
+        };
+        let normalize = Normalize::default();
+        assert_eq!(vec!["1", "2"], parse(normalize, "1\n2\n\n\n"));
+        assert_eq!(vec!["1", "", "2"], parse(normalize, "1\n\n2\r\n\r\n"));
+        assert_eq!(vec!["1", " "], parse(normalize, "1\n \n"));
+        let trim = Normalize { trailing: Trailing::TrimWhitespace, ..normalize };
+        assert_eq!(vec!["1 ", " 2"], parse(trim, "1 \n 2 \n \n\t\n"));
+        assert_eq!(vec!["1", "2", ""], parse(Normalize::NONE, "1\n2\n\n"));
+
+        // Just as when the whole file is read at once
+        for input in ["1\n2\n\n", "a \n\n b\n  \n", "\n\n", "x"] {
+            for normalize in [normalize, trim, Normalize::NONE] {
+                let whole: Vec<String> = normalize.apply(input.to_string()).lines().map(String::from).collect();
+                assert_eq!(whole, parse(normalize, input));
+            }
+        }
+    }
+
+    #[test]
+    fn maps_file() {
+        let path = std::env::temp_dir().join(format!("advent-shared-mapped-{}.txt", std::process::id()));
+        fs::write(&path, "1\n2\n3\n\n").unwrap();
+        let sum: u32 = mapped_lines_iter::<u32>(path.clone()).unwrap().map(Result::unwrap).sum();
+        let lines = mapped_lines_iter::<String>(path.clone()).unwrap().normalized(Normalize::NONE);
+        let kept = lines.collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(6, sum);
+        assert_eq!(vec!["1", "2", "3", ""], kept);
+
+        // A line that isn't UTF-8 says where it goes wrong
+        fs::write(&path, b"1\n\xe2\x82\xac2\xff\n").unwrap();
+        let errors: Vec<_> = mapped_lines_iter::<u32>(path.clone()).unwrap().filter_map(Result::err).collect();
+        fs::remove_file(path).unwrap();
+        assert_eq!(1, errors.len());
+        assert!(errors[0].to_string().starts_with("line 2, column 3: "));
+    }
+}
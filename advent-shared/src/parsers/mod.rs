@@ -1,8 +1,12 @@
 mod vec_of;
 mod identity;
 mod raw;
-mod pair;
+mod tuple;
+mod lines_iter;
+mod ints;
 pub use vec_of::*;
 pub use identity::*;
 pub use raw::*;
-pub use pair::*;
\ No newline at end of file
+pub use tuple::*;
+pub use lines_iter::*;
+pub use ints::*;
\ No newline at end of file
@@ -1,8 +1,16 @@
 use anyhow::*;
-use std::{path::PathBuf, fs};
+use crate::input::{read_input, read_input_with, Normalize};
+use std::path::PathBuf;
 
 pub fn raw(file: PathBuf) -> Result<String>
 {
-    let contents = fs::read_to_string(file)?;
+    let contents = read_input(&file)?;
     Ok(contents)
+}
+
+/// The file exactly as it is on disk, for puzzles where line endings or
+/// trailing whitespace are significant.
+pub fn verbatim(file: PathBuf) -> Result<String>
+{
+    read_input_with(&file, Normalize::NONE)
 }
\ No newline at end of file
@@ -0,0 +1,272 @@
+use crate::{input::{read_input_with, Normalize}, ParseError};
+use anyhow::Result;
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+/// How the fields of a tuple are separated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter<'a> {
+    /// Exactly this text; consecutive delimiters produce empty fields.
+    Str(&'a str),
+    /// Any run of whitespace, ignoring leading and trailing whitespace.
+    Whitespace,
+    /// A small regex: literal characters, `.`, `\s`, `\d`, `[...]` classes
+    /// (with ranges and `^` negation), each optionally followed by `?`, `*` or
+    /// `+`. For example `"[-: ]+"` splits `"1-3 a: abcde"` into four fields.
+    Pattern(&'a str),
+}
+
+impl Delimiter<'_> {
+    /// Splits `s` into its fields, along with the byte offset each starts at.
+    pub fn split<'s>(&self, s: &'s str) -> Result<Vec<(usize, &'s str)>, ParseError> {
+        Ok(match *self {
+            Delimiter::Str(delim) => {
+                let mut fields = vec![];
+                let mut start = 0;
+                for (i, _) in s.match_indices(delim) {
+                    fields.push((start, &s[start..i]));
+                    start = i + delim.len();
+                }
+                fields.push((start, &s[start..]));
+                fields
+            }
+            Delimiter::Whitespace => s
+                .split_whitespace()
+                .map(|field| (field.as_ptr() as usize - s.as_ptr() as usize, field))
+                .collect(),
+            Delimiter::Pattern(pattern) => {
+                let tokens = compile(pattern)?;
+                let mut fields = vec![];
+                let (mut start, mut search) = (0, 0);
+                while search <= s.len() {
+                    match matches(&tokens, s, search).filter(|&end| end > search) {
+                        Some(end) => {
+                            fields.push((start, &s[start..search]));
+                            start = end;
+                            search = end;
+                        }
+                        None => search += s[search..].chars().next().map_or(1, char::len_utf8),
+                    }
+                }
+                fields.push((start, &s[start..]));
+                fields
+            }
+        })
+    }
+}
+
+/// Tuples of up to eight `FromStr` fields that can be parsed from a delimited line.
+pub trait Tuple: Sized {
+    const ARITY: usize;
+    fn from_fields(s: &str, fields: &[(usize, &str)]) -> Result<Self, ParseError>;
+}
+
+macro_rules! tuple {
+    ($arity:expr => $($t:ident $i:tt),*) => {
+        impl<$($t),*> Tuple for ($($t,)*)
+        where
+            $($t: FromStr, <$t as FromStr>::Err: Display,)*
+        {
+            const ARITY: usize = $arity;
+            fn from_fields(s: &str, fields: &[(usize, &str)]) -> Result<Self, ParseError> {
+                Ok(($(parse_field::<$t>(s, fields[$i])?,)*))
+            }
+        }
+    };
+}
+tuple!(2 => A 0, B 1);
+tuple!(3 => A 0, B 1, C 2);
+tuple!(4 => A 0, B 1, C 2, D 3);
+tuple!(5 => A 0, B 1, C 2, D 3, E 4);
+tuple!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+tuple!(7 => A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple!(8 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+fn parse_field<T>(s: &str, (offset, text): (usize, &str)) -> Result<T, ParseError>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    text.parse::<T>().map_err(|e| ParseError::at(s, offset, format!("invalid value {:?}: {}", text, e)))
+}
+
+/// Parses `s` as exactly `T::ARITY` fields; missing or extra fields are errors.
+pub fn parse_tuple<T: Tuple>(s: &str, delimiter: Delimiter) -> Result<T, ParseError> {
+    let fields = delimiter.split(s)?;
+    if fields.len() < T::ARITY {
+        let message = format!("expected {} fields, found {}", T::ARITY, fields.len());
+        return Err(ParseError::at(s, s.len(), message));
+    }
+    if let Some(&(offset, _)) = fields.get(T::ARITY) {
+        return Err(ParseError::at(s, offset, format!("unexpected field after the first {}", T::ARITY)));
+    }
+    T::from_fields(s, &fields)
+}
+
+/// The whole file as a single tuple, e.g. `(u32, u32)` from `"246540-787419"`.
+pub fn tuple_with<T: Tuple>(file: PathBuf, delimiter: Delimiter) -> Result<T> {
+    tuple_with_normalize(file, delimiter, Normalize::default())
+}
+
+pub fn tuple_with_normalize<T: Tuple>(file: PathBuf, delimiter: Delimiter, normalize: Normalize) -> Result<T> {
+    let contents = read_input_with(&file, normalize)?;
+    Ok(parse_tuple(&contents, delimiter)?)
+}
+
+/// One tuple per line.
+pub fn vec_of_tuples<T: Tuple>(file: PathBuf, delimiter: Delimiter) -> Result<Vec<T>> {
+    vec_of_tuples_with(file, delimiter, Normalize::default())
+}
+
+pub fn vec_of_tuples_with<T: Tuple>(file: PathBuf, delimiter: Delimiter, normalize: Normalize) -> Result<Vec<T>> {
+    let contents = read_input_with(&file, normalize)?;
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| parse_tuple(line, delimiter).map_err(|e| e.on_line(i + 1).into()))
+        .collect()
+}
+
+#[derive(Debug)]
+enum Class {
+    Any,
+    Char(char),
+    Set { negated: bool, ranges: Vec<(char, char)> },
+}
+
+impl Class {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            Class::Any => true,
+            Class::Char(expected) => c == *expected,
+            Class::Set { negated, ranges } => ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Token {
+    class: Class,
+    min: usize,
+    max: usize,
+}
+
+fn compile(pattern: &str) -> Result<Vec<Token>, ParseError> {
+    fn escape(c: char) -> Class {
+        match c {
+            's' => Class::Set { negated: false, ranges: vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')] },
+            'd' => Class::Set { negated: false, ranges: vec![('0', '9')] },
+            c => Class::Char(c),
+        }
+    }
+
+    let error = |offset: usize, message: &str| ParseError::at(pattern, offset, message);
+    let mut tokens = vec![];
+    let mut chars = pattern.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let class = match c {
+            '.' => Class::Any,
+            '\\' => escape(chars.next().ok_or_else(|| error(i, "dangling '\\'"))?.1),
+            '[' => {
+                let negated = chars.next_if(|&(_, c)| c == '^').is_some();
+                let mut ranges = vec![];
+                loop {
+                    let lo = match chars.next() {
+                        Some((_, ']')) if !ranges.is_empty() => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => match escape(c) {
+                                Class::Set { ranges: escaped, .. } => {
+                                    ranges.extend(escaped);
+                                    continue;
+                                }
+                                _ => c,
+                            },
+                            None => return Err(error(i, "unclosed '['")),
+                        },
+                        Some((_, c)) => c,
+                        None => return Err(error(i, "unclosed '['")),
+                    };
+                    let is_range = chars.peek().map(|&(_, c)| c) == Some('-')
+                        && chars.clone().nth(1).is_some_and(|(_, c)| c != ']');
+                    if is_range {
+                        chars.next();
+                        let (_, hi) = chars.next().unwrap();
+                        ranges.push((lo, hi));
+                    } else {
+                        ranges.push((lo, lo));
+                    }
+                }
+                Class::Set { negated, ranges }
+            }
+            '?' | '*' | '+' => return Err(error(i, "quantifier with nothing to repeat")),
+            c => Class::Char(c),
+        };
+        let (min, max) = match chars.next_if(|&(_, c)| matches!(c, '?' | '*' | '+')) {
+            Some((_, '?')) => (0, 1),
+            Some((_, '*')) => (0, usize::MAX),
+            Some((_, '+')) => (1, usize::MAX),
+            _ => (1, 1),
+        };
+        tokens.push(Token { class, min, max });
+    }
+    Ok(tokens)
+}
+
+/// Where a match of `tokens` starting at `start` ends, preferring the longest.
+fn matches(tokens: &[Token], s: &str, start: usize) -> Option<usize> {
+    let Some((token, rest)) = tokens.split_first() else {
+        return Some(start);
+    };
+    let mut ends = vec![];
+    let mut end = start;
+    for c in s[start..].chars().take(token.max) {
+        if !token.class.contains(c) {
+            break;
+        }
+        end += c.len_utf8();
+        ends.push(end);
+    }
+    if token.min == 0 {
+        ends.insert(0, start);
+    } else if ends.len() < token.min {
+        return None;
+    } else {
+        ends.drain(..token.min - 1);
+    }
+    ends.into_iter().rev().find_map(|end| matches(rest, s, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heterogeneous_fields() {
+        let record: (usize, usize, char, String) = parse_tuple("1-3 a: abcde", Delimiter::Pattern("[-: ]+")).unwrap();
+        assert_eq!((1, 3, 'a', "abcde".to_string()), record);
+        let pair: (String, String) = parse_tuple("R8,U5", Delimiter::Str(",")).unwrap();
+        assert_eq!(("R8".to_string(), "U5".to_string()), pair);
+        let (a, b, c): (i32, f64, u8) = parse_tuple("  -4  2.5\t7 ", Delimiter::Whitespace).unwrap();
+        assert_eq!((-4, 2.5, 7), (a, b, c));
+    }
+
+    #[test]
+    fn strict_arity() {
+        let err = parse_tuple::<(u32, u32)>("1-2-3", Delimiter::Str("-")).unwrap_err();
+        assert_eq!(5, err.column);
+        let err = parse_tuple::<(u32, u32, u32)>("1-2", Delimiter::Str("-")).unwrap_err();
+        assert_eq!("column 4: expected 3 fields, found 2", err.to_string());
+        let err = parse_tuple::<(u32, char)>("12 ab", Delimiter::Whitespace).unwrap_err();
+        assert_eq!(4, err.column);
+    }
+
+    #[test]
+    fn patterns() {
+        let fields = |s, p| Delimiter::Pattern(p).split(s).unwrap().into_iter().map(|(_, f)| f).collect::<Vec<_>>();
+        assert_eq!(vec!["a", "b", "c"], fields("a, b,c", ",\\s*"));
+        assert_eq!(vec!["x", "y"], fields("x -> y", " ?-> ?"));
+        assert_eq!(vec!["abc", "def"], fields("abc123def", "[0-9]+"));
+        assert_eq!(vec!["a", "b"], fields("a..b", "\\.+"));
+        assert!(Delimiter::Pattern("[ab").split("a").is_err());
+        assert!(Delimiter::Pattern("+").split("a").is_err());
+    }
+}
@@ -1,12 +1,21 @@
 use anyhow::*;
-use std::{fmt::Debug, path::PathBuf, fs, str::FromStr};
+use crate::input::{read_input_with, Normalize};
+use std::{fmt::Debug, path::PathBuf, str::FromStr};
 
 pub fn vec_of<T>(file: PathBuf) -> Result<Vec<T>>
 where
     T: FromStr,
     <T as FromStr>::Err: Debug,
 {
-    let contents = fs::read_to_string(file)?;
+    vec_of_with(file, Normalize::default())
+}
+
+pub fn vec_of_with<T>(file: PathBuf, normalize: Normalize) -> Result<Vec<T>>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Debug,
+{
+    let contents = read_input_with(&file, normalize)?;
     Ok(contents
         .lines()
         .map(|l| l.parse::<T>())
@@ -19,7 +28,15 @@ where
     T: FromStr,
     <T as FromStr>::Err: Debug,
 {
-    let contents = fs::read_to_string(file)?;
+    vec_of_blank_lines_with(file, Normalize::default())
+}
+
+pub fn vec_of_blank_lines_with<T>(file: PathBuf, normalize: Normalize) -> Result<Vec<T>>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Debug,
+{
+    let contents = read_input_with(&file, normalize)?;
     Ok(contents.split("\n\n")
         .map(|l| l.parse::<T>())
         .map(|l| l.unwrap())
@@ -31,9 +48,53 @@ where
     T: FromStr,
     <T as FromStr>::Err: Debug,
 {
-    let contents = fs::read_to_string(file)?;
+    vec_of_commas_with(file, Normalize::default())
+}
+
+/// Splits on every comma, leaving each field exactly as it is, so the
+/// normalization has to take care of the newline at the end of the file.
+pub fn vec_of_commas_with<T>(file: PathBuf, normalize: Normalize) -> Result<Vec<T>>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Debug,
+{
+    let contents = read_input_with(&file, normalize)?;
     Ok(contents.split(",")
         .map(|l| l.parse::<T>())
         .map(|l| l.unwrap())
         .collect())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn with_file<R>(name: &str, contents: &str, f: impl FnOnce(PathBuf) -> R) -> R {
+        let path = std::env::temp_dir().join(format!("advent-shared-{}-{}.txt", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let result = f(path.clone());
+        fs::remove_file(path).unwrap();
+        result
+    }
+
+    #[test]
+    fn crlf_commas() {
+        let memory = with_file("crlf-commas", "1,0,0,3,99\r\n", vec_of_commas::<i64>).unwrap();
+        assert_eq!(vec![1, 0, 0, 3, 99], memory);
+    }
+
+    #[test]
+    fn opt_out() {
+        let lines = with_file("verbatim-lines", "a\r\n\r\n", |f| vec_of_with::<String>(f, Normalize::NONE)).unwrap();
+        assert_eq!(vec!["a", ""], lines);
+        let fields = with_file("verbatim-commas", "1, 2\n", |f| vec_of_commas_with::<String>(f, Normalize::NONE)).unwrap();
+        assert_eq!(vec!["1", " 2\n"], fields);
+    }
+
+    #[test]
+    fn crlf_blank_lines() {
+        let groups = with_file("crlf-blank-lines", "abc\r\n\r\na\r\nb\r\n\r\n", vec_of_blank_lines::<String>).unwrap();
+        assert_eq!(vec!["abc", "a\nb"], groups);
+    }
+}
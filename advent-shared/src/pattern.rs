@@ -0,0 +1,212 @@
+//! Matching lines against simple templates like `"{min}-{max} {letter}: {password}"`.
+//!
+//! A template is literal text interleaved with `{name}` or `{}` fields; `{{` and
+//! `}}` stand for literal braces. A field captures everything up to the first
+//! occurrence of the literal text that follows it (or the rest of the line, for a
+//! trailing field), so two fields must always be separated by some literal text.
+//! This is what `#[derive(AdventParse)]` generates its `FromStr` impls on top of.
+
+use crate::ParseError;
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Literal(Cow<'a, str>),
+    Field(Option<&'a str>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern<'a> {
+    segments: Vec<Segment<'a>>,
+}
+
+impl<'a> Pattern<'a> {
+    pub fn new(template: &'a str) -> Result<Self, ParseError> {
+        // Literal text stays borrowed from the template unless a `{{` or `}}` splits it.
+        fn extend<'a>(literal: &mut Cow<'a, str>, text: &'a str) {
+            if literal.is_empty() {
+                *literal = Cow::Borrowed(text);
+            } else if !text.is_empty() {
+                literal.to_mut().push_str(text);
+            }
+        }
+
+        let bytes = template.as_bytes();
+        let mut segments = vec![];
+        let mut literal = Cow::Borrowed("");
+        let (mut i, mut literal_start) = (0, 0);
+        while i < bytes.len() {
+            match bytes[i] {
+                b'{' | b'}' if bytes.get(i + 1) == Some(&bytes[i]) => {
+                    // `{{` and `}}` keep the first brace as literal text and drop the second.
+                    extend(&mut literal, &template[literal_start..=i]);
+                    i += 2;
+                    literal_start = i;
+                }
+                b'{' => {
+                    extend(&mut literal, &template[literal_start..i]);
+                    let len = template[i..]
+                        .find('}')
+                        .ok_or_else(|| ParseError::at(template, i, "unclosed '{' in pattern"))?;
+                    let name = &template[i + 1..i + len];
+                    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                        return Err(ParseError::at(template, i, format!("invalid field name {:?}", name)));
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    } else if let Some(Segment::Field(_)) = segments.last() {
+                        return Err(ParseError::at(template, i, "fields must be separated by literal text"));
+                    }
+                    segments.push(Segment::Field(if name.is_empty() { None } else { Some(name) }));
+                    i += len + 1;
+                    literal_start = i;
+                }
+                b'}' => return Err(ParseError::at(template, i, "unmatched '}' in pattern")),
+                _ => i += 1,
+            }
+        }
+        extend(&mut literal, &template[literal_start..]);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Pattern { segments })
+    }
+
+    pub fn segments(&self) -> &[Segment<'a>] {
+        &self.segments
+    }
+
+    pub fn captures<'s>(&self, input: &'s str) -> Result<Captures<'s>, ParseError> {
+        captures(&self.segments, input)
+    }
+}
+
+/// The text captured by each field of a pattern, in template order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures<'s> {
+    input: &'s str,
+    fields: Vec<(usize, &'s str)>,
+}
+
+impl<'s> Captures<'s> {
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'s str> {
+        self.fields.get(index).map(|&(_, text)| text)
+    }
+
+    /// Parses the `index`th field, reporting failures at the field's column.
+    pub fn parse<T>(&self, index: usize) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        <T as FromStr>::Err: Display,
+    {
+        let (offset, text) = self.fields[index];
+        text.parse::<T>()
+            .map_err(|e| ParseError::at(self.input, offset, format!("invalid value {:?}: {}", text, e)))
+    }
+}
+
+/// Matches `input` against an already split template; see [`Pattern::captures`].
+pub fn captures<'s>(segments: &[Segment], input: &'s str) -> Result<Captures<'s>, ParseError> {
+    let mut pos = 0;
+    let mut fields = vec![];
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Literal(literal) => {
+                if !input[pos..].starts_with(literal.as_ref()) {
+                    return Err(ParseError::at(input, pos, format!("expected {:?}", literal)));
+                }
+                pos += literal.len();
+            }
+            Segment::Field(_) => {
+                let end = match segments.get(i + 1) {
+                    Some(Segment::Literal(next)) => input[pos..]
+                        .find(next.as_ref())
+                        .map(|len| pos + len)
+                        .ok_or_else(|| ParseError::at(input, input.len(), format!("expected {:?}", next)))?,
+                    _ => input.len(),
+                };
+                fields.push((pos, &input[pos..end]));
+                pos = end;
+            }
+        }
+    }
+    if pos != input.len() {
+        return Err(ParseError::at(input, pos, format!("unexpected trailing input {:?}", &input[pos..])));
+    }
+    Ok(Captures { input, fields })
+}
+
+/// Keeps whichever error got further into the input, for reporting the most
+/// relevant failure when several patterns were tried in turn.
+pub fn furthest(best: Option<ParseError>, error: ParseError) -> ParseError {
+    match best {
+        Some(best) if best.column >= error.column => best,
+        _ => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_segments() {
+        use Segment::*;
+        let pattern = Pattern::new("{min}-{max} {letter}: {password}").unwrap();
+        assert_eq!(
+            pattern.segments(),
+            &[
+                Field(Some("min")),
+                Literal("-".into()),
+                Field(Some("max")),
+                Literal(" ".into()),
+                Field(Some("letter")),
+                Literal(": ".into()),
+                Field(Some("password")),
+            ]
+        );
+        let pattern = Pattern::new("{{{}}}").unwrap();
+        assert_eq!(pattern.segments(), &[Literal("{".into()), Field(None), Literal("}".into())]);
+        let pattern = Pattern::new("a{{b}}{c}d").unwrap();
+        assert_eq!(pattern.segments(), &[Literal("a{b}".into()), Field(Some("c")), Literal("d".into())]);
+    }
+
+    #[test]
+    fn template_errors() {
+        assert_eq!(Pattern::new("{a}{b}").unwrap_err().column, 4);
+        assert_eq!(Pattern::new("acc {").unwrap_err().column, 5);
+        assert_eq!(Pattern::new("a}").unwrap_err().column, 2);
+        assert_eq!(Pattern::new("a { b }").unwrap_err().column, 3);
+    }
+
+    #[test]
+    fn captures_fields() {
+        let pattern = Pattern::new("{min}-{max} {letter}: {password}").unwrap();
+        let captures = pattern.captures("1-3 a: abcde").unwrap();
+        assert_eq!(4, captures.len());
+        assert_eq!(Ok(3), captures.parse::<usize>(1));
+        assert_eq!(Ok('a'), captures.parse::<char>(2));
+        assert_eq!(Some("abcde"), captures.get(3));
+    }
+
+    #[test]
+    fn located_errors() {
+        let pattern = Pattern::new("acc {}").unwrap();
+        assert_eq!(1, pattern.captures("jmp +1").unwrap_err().column);
+        let captures = pattern.captures("acc +x").unwrap();
+        assert_eq!(5, captures.parse::<i32>(0).unwrap_err().column);
+
+        let pattern = Pattern::new("{a}-{b}").unwrap();
+        assert_eq!(4, pattern.captures("123").unwrap_err().column);
+        let pattern = Pattern::new("<{}>").unwrap();
+        assert_eq!(4, pattern.captures("<1>!").unwrap_err().column);
+    }
+}